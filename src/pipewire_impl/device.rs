@@ -0,0 +1,178 @@
+use std::io::Cursor;
+
+use pipewire::spa::{
+    param::ParamType,
+    pod::{
+        deserialize::PodDeserializer, serialize::PodSerializer, Object, Pod, Property, Value,
+        ValueArray,
+    },
+    sys as spa_sys,
+    utils::Id,
+};
+
+use super::PortType;
+
+/// A profile of a device, like "Analog Stereo Duplex" or "High Fidelity Playback (A2DP Sink)"
+#[derive(Debug, Clone)]
+pub struct Profile {
+    pub index: i32,
+    pub name: String,
+    pub description: String,
+    pub available: bool,
+}
+
+/// A route of a device, the physical destination of its audio, like speakers or headphones
+#[derive(Debug, Clone)]
+pub struct Route {
+    pub index: i32,
+    /// Index of the card device this route belongs to
+    pub device: i32,
+    pub direction: PortType,
+    pub name: String,
+    pub description: String,
+    pub available: bool,
+    /// Indices of the profiles this route can be used with
+    pub profiles: Vec<i32>,
+}
+
+/// A change to one of the profile or route params of a device
+#[derive(Debug, Clone)]
+pub enum DeviceUpdate {
+    /// One of the available profiles (`EnumProfile` param), an index of 0 starts a new enumeration
+    Profiles { index: u32, profile: Profile },
+    /// The active profile (`Profile` param)
+    ActiveProfile(Option<i32>),
+    /// One of the available routes (`EnumRoute` param), an index of 0 starts a new enumeration
+    Routes { index: u32, route: Route },
+    /// The active route of one of the card devices (`Route` param), an index of 0 starts a new enumeration
+    ActiveRoutes { index: u32, route: Route },
+}
+
+impl DeviceUpdate {
+    pub fn from_param(id: ParamType, index: u32, param: Option<&Pod>) -> Option<Self> {
+        if id == ParamType::Profile && param.is_none() {
+            return Some(DeviceUpdate::ActiveProfile(None));
+        }
+
+        let properties = match PodDeserializer::deserialize_any_from(param?.as_bytes()) {
+            Ok((_, Value::Object(object))) => object.properties,
+            _ => return None,
+        };
+
+        if id == ParamType::EnumProfile {
+            let profile = parse_profile(&properties)?;
+
+            Some(DeviceUpdate::Profiles { index, profile })
+        } else if id == ParamType::Profile {
+            let profile = parse_profile(&properties)?;
+
+            Some(DeviceUpdate::ActiveProfile(Some(profile.index)))
+        } else if id == ParamType::EnumRoute {
+            let route = parse_route(&properties)?;
+
+            Some(DeviceUpdate::Routes { index, route })
+        } else if id == ParamType::Route {
+            let route = parse_route(&properties)?;
+
+            Some(DeviceUpdate::ActiveRoutes { index, route })
+        } else {
+            None
+        }
+    }
+}
+
+fn parse_profile(properties: &[Property]) -> Option<Profile> {
+    let mut profile = Profile {
+        index: -1,
+        name: String::new(),
+        description: String::new(),
+        available: true,
+    };
+
+    for property in properties {
+        match (property.key, &property.value) {
+            (spa_sys::SPA_PARAM_PROFILE_index, Value::Int(index)) => profile.index = *index,
+            (spa_sys::SPA_PARAM_PROFILE_name, Value::String(name)) => profile.name = name.clone(),
+            (spa_sys::SPA_PARAM_PROFILE_description, Value::String(description)) => {
+                profile.description = description.clone()
+            }
+            (spa_sys::SPA_PARAM_PROFILE_available, Value::Id(Id(available))) => {
+                profile.available = *available != spa_sys::SPA_PARAM_AVAILABILITY_no
+            }
+            _ => {}
+        }
+    }
+
+    (profile.index >= 0).then_some(profile)
+}
+
+fn parse_route(properties: &[Property]) -> Option<Route> {
+    let mut route = Route {
+        index: -1,
+        device: -1,
+        direction: PortType::Unknown,
+        name: String::new(),
+        description: String::new(),
+        available: true,
+        profiles: Vec::new(),
+    };
+
+    for property in properties {
+        match (property.key, &property.value) {
+            (spa_sys::SPA_PARAM_ROUTE_index, Value::Int(index)) => route.index = *index,
+            (spa_sys::SPA_PARAM_ROUTE_device, Value::Int(device)) => route.device = *device,
+            (spa_sys::SPA_PARAM_ROUTE_direction, Value::Id(Id(direction))) => {
+                route.direction = match *direction {
+                    spa_sys::SPA_DIRECTION_INPUT => PortType::Input,
+                    spa_sys::SPA_DIRECTION_OUTPUT => PortType::Output,
+                    _ => PortType::Unknown,
+                }
+            }
+            (spa_sys::SPA_PARAM_ROUTE_name, Value::String(name)) => route.name = name.clone(),
+            (spa_sys::SPA_PARAM_ROUTE_description, Value::String(description)) => {
+                route.description = description.clone()
+            }
+            (spa_sys::SPA_PARAM_ROUTE_available, Value::Id(Id(available))) => {
+                route.available = *available != spa_sys::SPA_PARAM_AVAILABILITY_no
+            }
+            (spa_sys::SPA_PARAM_ROUTE_profiles, Value::ValueArray(ValueArray::Int(profiles))) => {
+                route.profiles = profiles.clone()
+            }
+            _ => {}
+        }
+    }
+
+    (route.index >= 0).then_some(route)
+}
+
+/// Builds the param used to switch a device to another profile
+pub fn profile_param(index: i32) -> Vec<u8> {
+    serialize(Object {
+        type_: spa_sys::SPA_TYPE_OBJECT_ParamProfile,
+        id: spa_sys::SPA_PARAM_Profile,
+        properties: vec![
+            Property::new(spa_sys::SPA_PARAM_PROFILE_index, Value::Int(index)),
+            Property::new(spa_sys::SPA_PARAM_PROFILE_save, Value::Bool(true)),
+        ],
+    })
+}
+
+/// Builds the param used to switch a card device to another route
+pub fn route_param(device: i32, index: i32) -> Vec<u8> {
+    serialize(Object {
+        type_: spa_sys::SPA_TYPE_OBJECT_ParamRoute,
+        id: spa_sys::SPA_PARAM_Route,
+        properties: vec![
+            Property::new(spa_sys::SPA_PARAM_ROUTE_index, Value::Int(index)),
+            Property::new(spa_sys::SPA_PARAM_ROUTE_device, Value::Int(device)),
+            Property::new(spa_sys::SPA_PARAM_ROUTE_save, Value::Bool(true)),
+        ],
+    })
+}
+
+fn serialize(object: Object) -> Vec<u8> {
+    PodSerializer::serialize(Cursor::new(Vec::new()), &Value::Object(object))
+        .expect("Failed to serialize device param")
+        .0
+        .into_inner()
+}
@@ -0,0 +1,297 @@
+use std::fmt;
+
+use pipewire::spa::{
+    param::{
+        audio::AudioInfoRaw,
+        format::{MediaSubtype, MediaType},
+        format_utils,
+        video::VideoInfoRaw,
+        ParamType,
+    },
+    pod::Pod,
+};
+
+/// A simplified description of a format param, enough to be displayed in the ui and to compare formats of linked ports
+#[derive(Debug, Clone, PartialEq)]
+pub enum Format {
+    Audio {
+        sample_format: String,
+        rate: u32,
+        channels: u32,
+    },
+    Video {
+        pixel_format: String,
+        width: u32,
+        height: u32,
+        framerate: (u32, u32),
+    },
+    Other {
+        media_type: String,
+        media_subtype: String,
+    },
+}
+
+impl Format {
+    pub fn parse(pod: &Pod) -> Option<Self> {
+        let (media_type, media_subtype) = format_utils::parse_format(pod).ok()?;
+
+        if media_type == MediaType::Audio && media_subtype == MediaSubtype::Raw {
+            let mut info = AudioInfoRaw::new();
+            if info.parse(pod).is_ok() {
+                return Some(Format::Audio {
+                    sample_format: short_name(format!("{:?}", info.format())),
+                    rate: info.rate(),
+                    channels: info.channels(),
+                });
+            }
+        } else if media_type == MediaType::Video && media_subtype == MediaSubtype::Raw {
+            let mut info = VideoInfoRaw::new();
+            if info.parse(pod).is_ok() {
+                let size = info.size();
+                let framerate = info.framerate();
+                return Some(Format::Video {
+                    pixel_format: short_name(format!("{:?}", info.format())),
+                    width: size.width,
+                    height: size.height,
+                    framerate: (framerate.num, framerate.denom),
+                });
+            }
+        }
+
+        // Enumerated formats usually contain choices which the raw parsers can't handle
+        Some(Format::Other {
+            media_type: short_name(format!("{:?}", media_type)).to_lowercase(),
+            media_subtype: short_name(format!("{:?}", media_subtype)).to_lowercase(),
+        })
+    }
+    /// Whether data can flow between two ports using these formats without any conversion
+    pub fn is_compatible(&self, other: &Format) -> bool {
+        match (self, other) {
+            (
+                Format::Audio {
+                    sample_format,
+                    rate,
+                    channels,
+                },
+                Format::Audio {
+                    sample_format: other_sample_format,
+                    rate: other_rate,
+                    channels: other_channels,
+                },
+            ) => {
+                sample_format == other_sample_format
+                    && channels == other_channels
+                    && (*rate == 0 || *other_rate == 0 || rate == other_rate)
+            }
+            (
+                Format::Video {
+                    pixel_format,
+                    width,
+                    height,
+                    ..
+                },
+                Format::Video {
+                    pixel_format: other_pixel_format,
+                    width: other_width,
+                    height: other_height,
+                    ..
+                },
+            ) => {
+                pixel_format == other_pixel_format && width == other_width && height == other_height
+            }
+            (
+                Format::Other { media_type, .. },
+                Format::Other {
+                    media_type: other_media_type,
+                    ..
+                },
+            ) => media_type == other_media_type,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Format::Audio {
+                sample_format,
+                rate,
+                channels,
+            } => {
+                if *rate == 0 {
+                    write!(f, "{} {}ch", sample_format, channels)
+                } else {
+                    write!(f, "{} {}Hz {}ch", sample_format, rate, channels)
+                }
+            }
+            Format::Video {
+                pixel_format,
+                width,
+                height,
+                framerate: (num, denom),
+            } => {
+                if *denom == 0 {
+                    write!(f, "{} {}x{}", pixel_format, width, height)
+                } else {
+                    write!(
+                        f,
+                        "{} {}x{} @ {:.2}fps",
+                        pixel_format,
+                        width,
+                        height,
+                        *num as f32 / *denom as f32
+                    )
+                }
+            }
+            Format::Other {
+                media_type,
+                media_subtype,
+            } => write!(f, "{}/{}", media_type, media_subtype),
+        }
+    }
+}
+
+/// A change to one of the format params of a node or a port
+#[derive(Debug, Clone)]
+pub enum FormatUpdate {
+    /// The currently negotiated format (`Format` param), `None` if it was cleared
+    Negotiated(Option<Format>),
+    /// One of the supported formats (`EnumFormat` param), an index of 0 starts a new enumeration
+    Supported { index: u32, format: Format },
+}
+
+impl FormatUpdate {
+    pub fn from_param(id: ParamType, index: u32, param: Option<&Pod>) -> Option<Self> {
+        if id == ParamType::Format {
+            Some(FormatUpdate::Negotiated(param.and_then(Format::parse)))
+        } else if id == ParamType::EnumFormat {
+            let format = Format::parse(param?)?;
+
+            Some(FormatUpdate::Supported { index, format })
+        } else {
+            None
+        }
+    }
+}
+
+/// Strips the type prefix from the debug representation of spa enums, "AudioFormat::F32LE" -> "F32LE"
+fn short_name(debug: String) -> String {
+    match debug.rsplit_once("::") {
+        Some((_, name)) => name.to_string(),
+        None => debug,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pipewire::spa::{
+        param::audio::AudioFormat,
+        pod::{serialize::PodSerializer, Object, Property, Value},
+        sys as spa_sys,
+        utils::Id,
+    };
+    use std::io::Cursor;
+
+    fn parse(properties: Vec<Property>) -> Option<Format> {
+        let object = Value::Object(Object {
+            type_: spa_sys::SPA_TYPE_OBJECT_Format,
+            id: spa_sys::SPA_PARAM_EnumFormat,
+            properties,
+        });
+        let bytes = PodSerializer::serialize(Cursor::new(Vec::new()), &object)
+            .expect("Failed to serialize format")
+            .0
+            .into_inner();
+
+        Format::parse(Pod::from_bytes(&bytes)?)
+    }
+
+    fn audio(sample_format: &str, rate: u32, channels: u32) -> Format {
+        Format::Audio {
+            sample_format: sample_format.to_string(),
+            rate,
+            channels,
+        }
+    }
+
+    fn video(width: u32, height: u32, framerate: (u32, u32)) -> Format {
+        Format::Video {
+            pixel_format: "RGBA".to_string(),
+            width,
+            height,
+            framerate,
+        }
+    }
+
+    #[test]
+    fn parse_raw_audio() {
+        let mut info = AudioInfoRaw::new();
+        info.set_format(AudioFormat::F32LE);
+        info.set_rate(48000);
+        info.set_channels(2);
+
+        assert_eq!(parse(info.into()), Some(audio("F32LE", 48000, 2)));
+    }
+
+    #[test]
+    fn parse_other_media() {
+        let properties = vec![
+            Property::new(
+                spa_sys::SPA_FORMAT_mediaType,
+                Value::Id(Id(spa_sys::SPA_MEDIA_TYPE_application)),
+            ),
+            Property::new(
+                spa_sys::SPA_FORMAT_mediaSubtype,
+                Value::Id(Id(spa_sys::SPA_MEDIA_SUBTYPE_control)),
+            ),
+        ];
+
+        assert_eq!(
+            parse(properties),
+            Some(Format::Other {
+                media_type: "application".to_string(),
+                media_subtype: "control".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn parse_without_media_type() {
+        assert_eq!(parse(Vec::new()), None);
+    }
+
+    #[test]
+    fn audio_compatibility() {
+        let format = audio("F32LE", 48000, 2);
+
+        assert!(format.is_compatible(&audio("F32LE", 48000, 2)));
+        // A rate of 0 means any rate
+        assert!(format.is_compatible(&audio("F32LE", 0, 2)));
+        assert!(!format.is_compatible(&audio("F32LE", 44100, 2)));
+        assert!(!format.is_compatible(&audio("S16LE", 48000, 2)));
+        assert!(!format.is_compatible(&audio("F32LE", 48000, 1)));
+    }
+
+    #[test]
+    fn video_compatibility() {
+        let format = video(1920, 1080, (30, 1));
+
+        // Frames can be dropped or repeated
+        assert!(format.is_compatible(&video(1920, 1080, (60, 1))));
+        assert!(!format.is_compatible(&video(1280, 720, (30, 1))));
+        assert!(!format.is_compatible(&audio("F32LE", 48000, 2)));
+    }
+
+    #[test]
+    fn other_compatibility() {
+        let other = |media_type: &str, media_subtype: &str| Format::Other {
+            media_type: media_type.to_string(),
+            media_subtype: media_subtype.to_string(),
+        };
+
+        assert!(other("application", "control").is_compatible(&other("application", "midi")));
+        assert!(!other("application", "control").is_compatible(&other("audio", "dsp")));
+    }
+}
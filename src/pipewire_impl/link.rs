@@ -0,0 +1,144 @@
+use pipewire::{
+    properties::{properties, PropertiesBox},
+    spa::utils::dict::DictRef,
+};
+use serde::{Deserialize, Serialize};
+
+/// Set on every link created by pw-viz, so they can be told apart from the ones created by the session manager
+const CREATED_BY_KEY: &str = "pw-viz.created";
+
+/// Properties of links created by pw-viz
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LinkOptions {
+    /// Whether the link should outlive pw-viz
+    pub linger: bool,
+    /// Passive links don't keep their nodes running
+    pub passive: bool,
+    /// Feedback links can form loops in the graph
+    pub feedback: bool,
+}
+
+impl Default for LinkOptions {
+    fn default() -> Self {
+        // Links used to always linger
+        Self {
+            linger: true,
+            passive: false,
+            feedback: false,
+        }
+    }
+}
+
+impl LinkOptions {
+    /// Links recreated on behalf of another client, like when undoing their removal, aren't tagged as created by pw-viz
+    pub fn properties(
+        &self,
+        origin: LinkOrigin,
+        from_node: u32,
+        from_port: u32,
+        to_node: u32,
+        to_port: u32,
+    ) -> PropertiesBox {
+        let mut properties = properties! {
+            "link.input.port" => to_port.to_string(),
+            "link.output.port" => from_port.to_string(),
+            "link.input.node" => to_node.to_string(),
+            "link.output.node" => from_node.to_string(),
+            "object.linger" => if self.linger { "true" } else { "false" },
+            "link.passive" => if self.passive { "true" } else { "false" },
+            "link.feedback" => if self.feedback { "true" } else { "false" }
+        };
+
+        if origin.created_by_pw_viz() {
+            properties.insert(CREATED_BY_KEY, "true");
+        }
+
+        properties
+    }
+    /// Origin of the links created by pw-viz with these options
+    pub fn origin(&self) -> LinkOrigin {
+        if self.linger {
+            LinkOrigin::Lingering
+        } else {
+            LinkOrigin::Owned
+        }
+    }
+}
+
+/// Who created a link, and whether it goes away with pw-viz
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkOrigin {
+    /// Created by pw-viz and destroyed when it exits
+    Owned,
+    /// Created by pw-viz, outlives it
+    Lingering,
+    /// Created by the session manager or another client
+    External,
+}
+
+impl LinkOrigin {
+    fn from_props(props: &DictRef) -> Self {
+        if props.get(CREATED_BY_KEY).is_none() {
+            return Self::External;
+        }
+
+        match props.get("object.linger") {
+            Some(value) if is_true(value) => Self::Lingering,
+            _ => Self::Owned,
+        }
+    }
+    pub fn created_by_pw_viz(&self) -> bool {
+        *self != Self::External
+    }
+}
+
+/// Properties of an existing link
+#[derive(Debug, Clone)]
+pub struct LinkProperties {
+    pub origin: LinkOrigin,
+    pub passive: bool,
+    pub feedback: bool,
+    /// Every property of the link, shown when hovering over it
+    pub all: Vec<(String, String)>,
+}
+
+impl LinkProperties {
+    pub fn from_props(props: Option<&DictRef>) -> Self {
+        let props = match props {
+            Some(props) => props,
+            None => {
+                return Self {
+                    origin: LinkOrigin::External,
+                    passive: false,
+                    feedback: false,
+                    all: Vec::new(),
+                }
+            }
+        };
+
+        let flag = |key| props.get(key).map_or(false, is_true);
+
+        Self {
+            origin: LinkOrigin::from_props(props),
+            passive: flag("link.passive"),
+            feedback: flag("link.feedback"),
+            all: props
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        }
+    }
+    /// Options to recreate the link with
+    pub fn options(&self) -> LinkOptions {
+        LinkOptions {
+            linger: self.origin != LinkOrigin::Owned,
+            passive: self.passive,
+            feedback: self.feedback,
+        }
+    }
+}
+
+fn is_true(value: &str) -> bool {
+    value == "true" || value == "1"
+}
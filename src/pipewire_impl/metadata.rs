@@ -0,0 +1,118 @@
+/// The metadata object used by the session manager to store default devices and stream targets
+pub const DEFAULT_METADATA: &str = "default";
+/// The metadata object holding the global settings of the pipewire daemon, like its clock
+pub const SETTINGS_METADATA: &str = "settings";
+/// Key set on stream nodes to make the session manager link them to a specific node
+pub const TARGET_OBJECT: &str = "target.object";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DefaultKind {
+    Sink,
+    Source,
+}
+
+impl DefaultKind {
+    /// Metadata key holding the default chosen by the user
+    pub fn configured_key(&self) -> &'static str {
+        match self {
+            DefaultKind::Sink => "default.configured.audio.sink",
+            DefaultKind::Source => "default.configured.audio.source",
+        }
+    }
+    /// Parses a key of the default metadata, returns whether the key refers to the configured default
+    pub fn from_key(key: &str) -> Option<(DefaultKind, bool)> {
+        match key {
+            "default.audio.sink" => Some((DefaultKind::Sink, false)),
+            "default.audio.source" => Some((DefaultKind::Source, false)),
+            "default.configured.audio.sink" => Some((DefaultKind::Sink, true)),
+            "default.configured.audio.source" => Some((DefaultKind::Source, true)),
+            _ => None,
+        }
+    }
+    /// Kind of node a stream of the given media.class can be moved to
+    pub fn for_stream(media_class: &str) -> Option<DefaultKind> {
+        match media_class {
+            "Stream/Output/Audio" => Some(DefaultKind::Sink),
+            "Stream/Input/Audio" => Some(DefaultKind::Source),
+            _ => None,
+        }
+    }
+    /// Whether a node of the given media.class can be used as this kind of default
+    pub fn accepts(&self, media_class: &str) -> bool {
+        match self {
+            DefaultKind::Sink => media_class == "Audio/Sink" || media_class == "Audio/Duplex",
+            DefaultKind::Source => {
+                media_class == "Audio/Source"
+                    || media_class == "Audio/Source/Virtual"
+                    || media_class == "Audio/Duplex"
+            }
+        }
+    }
+}
+
+/// Default nodes are stored as JSON objects, like `{ "name": "alsa_output.pci-0000_00_1f.3.analog-stereo" }`
+pub fn parse_name(value: &str) -> Option<String> {
+    let (_, rest) = value.split_once("\"name\"")?;
+    let (_, rest) = rest.split_once(':')?;
+    let (_, rest) = rest.split_once('"')?;
+
+    let mut name = String::new();
+    let mut chars = rest.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => name.push(chars.next()?),
+            '"' => return Some(name),
+            c => name.push(c),
+        }
+    }
+
+    None
+}
+
+pub fn format_name(name: &str) -> String {
+    format!(
+        "{{ \"name\": \"{}\" }}",
+        name.replace('\\', "\\\\").replace('"', "\\\"")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_plain_name() {
+        assert_eq!(
+            parse_name(r#"{ "name": "alsa_output.pci-0000_00_1f.3.analog-stereo" }"#),
+            Some("alsa_output.pci-0000_00_1f.3.analog-stereo".to_string())
+        );
+        assert_eq!(parse_name(r#"{"name":"sink"}"#), Some("sink".to_string()));
+    }
+
+    #[test]
+    fn parse_escaped_name() {
+        assert_eq!(
+            parse_name(r#"{ "name": "my \"quoted\" sink \\ 1" }"#),
+            Some(r#"my "quoted" sink \ 1"#.to_string())
+        );
+    }
+
+    #[test]
+    fn parse_missing_name() {
+        assert_eq!(parse_name(""), None);
+        assert_eq!(parse_name("{}"), None);
+        assert_eq!(parse_name(r#"{ "other": "sink" }"#), None);
+        assert_eq!(parse_name(r#"{ "name": 3 }"#), None);
+        // Unterminated strings
+        assert_eq!(parse_name(r#"{ "name": "sink"#), None);
+        assert_eq!(parse_name(r#"{ "name": "sink\"#), None);
+    }
+
+    #[test]
+    fn format_round_trips() {
+        for name in ["sink", "my \"quoted\" sink", "back\\slash", ""] {
+            assert_eq!(parse_name(&format_name(name)), Some(name.to_string()));
+        }
+        assert_eq!(format_name("a\"b"), r#"{ "name": "a\"b" }"#);
+    }
+}
@@ -1,24 +1,55 @@
+mod device;
+mod format;
+mod link;
+mod metadata;
+mod profiler;
+mod replacement;
+mod scheduling;
 mod state;
+mod virtual_device;
 
 use pipewire::{
     context::ContextRc,
     core::Core,
     link::LinkChangeMask,
     main_loop::MainLoopRc,
+    proxy::ProxyT,
     registry::{GlobalObject, RegistryRc},
-    spa::utils::dict::DictRef,
+    spa::{param::ParamType, pod::Pod, utils::dict::DictRef},
+};
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    rc::Rc,
+    sync::mpsc::Sender,
 };
-use std::{cell::RefCell, collections::HashMap, rc::Rc, sync::mpsc::Sender};
 
 use crate::ui::UiMessage;
+use profiler::Profiler;
+use replacement::{Replacement, Replacements, Settle};
 use state::State;
 
+pub use device::{DeviceUpdate, Profile, Route};
+pub use format::{Format, FormatUpdate};
+pub use link::{LinkOptions, LinkOrigin, LinkProperties};
+pub use metadata::{DefaultKind, DEFAULT_METADATA, SETTINGS_METADATA};
+pub use profiler::{DriverProfile, ProfilerInfo, ProfilerSample};
+pub use scheduling::Scheduling;
+pub use virtual_device::{VirtualDevice, VirtualDeviceKind};
+
 pub enum PipewireMessage {
     NodeAdded {
         id: u32,
         name: String,
+        /// node.name, unlike `name` this is stable and unique
+        pw_name: String,
         description: Option<String>,
+        media_class: Option<String>,
         media_type: Option<MediaType>,
+        /// Pipewire id of the device this node belongs to
+        device_id: Option<u32>,
+        /// Pipewire id of the client which created this node
+        client_id: Option<u32>,
     },
     PortAdded {
         node_name: String,
@@ -26,6 +57,9 @@ pub enum PipewireMessage {
         id: u32,
         name: String,
         port_type: PortType,
+        media_type: Option<MediaType>,
+        channel: Option<String>,
+        flags: PortFlags,
     },
     LinkAdded {
         id: u32,
@@ -34,6 +68,7 @@ pub enum PipewireMessage {
 
         from_port: u32,
         to_port: u32,
+        properties: LinkProperties,
     },
     LinkStateChanged {
         id: u32,
@@ -51,23 +86,116 @@ pub enum PipewireMessage {
     LinkRemoved {
         id: u32,
     },
+    NodeFormatChanged {
+        name: String,
+        id: u32,
+        update: FormatUpdate,
+    },
+    NodeSchedulingChanged {
+        name: String,
+        id: u32,
+        scheduling: Scheduling,
+    },
+    PortFormatChanged {
+        node_name: String,
+        node_id: u32,
+        id: u32,
+        update: FormatUpdate,
+    },
+    DefaultNodeChanged {
+        kind: DefaultKind,
+        /// Whether this is the default picked by the user or the one currently in use
+        configured: bool,
+        /// node.name of the default node
+        pw_name: Option<String>,
+    },
+    StreamTargetChanged {
+        /// Pipewire id of the stream node
+        id: u32,
+        /// Value of target.object, the node.name or object.serial of the target
+        target: Option<String>,
+    },
+    MetadataRemoved {
+        name: String,
+        id: u32,
+    },
+    DeviceAdded {
+        id: u32,
+        name: String,
+        /// device.name of the device
+        pw_name: String,
+    },
+    DeviceChanged {
+        id: u32,
+        update: DeviceUpdate,
+    },
+    DeviceRemoved {
+        id: u32,
+    },
+    ClientAdded {
+        id: u32,
+        /// application.name, falls back to the binary name
+        name: String,
+        pid: Option<u32>,
+        binary: Option<String>,
+    },
+    ClientRemoved {
+        id: u32,
+    },
+    Profile(ProfilerSample),
+    ProfilerRemoved,
+    MetadataAdded {
+        name: String,
+        id: u32,
+    },
+    MetadataPropertyChanged {
+        /// metadata.name of the metadata object
+        metadata: String,
+        subject: u32,
+        /// `None` if all properties of the subject were removed
+        key: Option<String>,
+        type_: Option<String>,
+        value: Option<String>,
+    },
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum MediaType {
     Audio,
     Video,
     Midi,
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum PortType {
     Input,
     Output,
     Unknown,
 }
 
-type Proxies = HashMap<u32, ProxyLink>;
+/// Boolean port properties that describe what a port is connected to
+#[derive(Copy, Clone, Debug, Default)]
+pub struct PortFlags {
+    /// The port taps the signal of another port, like the monitor outputs of a sink
+    pub monitor: bool,
+    /// The port corresponds to a physical connector of a device
+    pub physical: bool,
+    /// Data enters or leaves the graph through this port
+    pub terminal: bool,
+}
+
+type Proxies = HashMap<u32, Proxy>;
+
+/// Proxies of bound objects, these need to be kept alive for their listeners to be called
+#[allow(dead_code)]
+enum Proxy {
+    Link(ProxyLink),
+    Node(ProxyNode),
+    Port(ProxyPort),
+    Metadata(ProxyMetadata),
+    Device(ProxyDevice),
+    Profiler(Profiler),
+}
 
 #[allow(dead_code)]
 struct ProxyLink {
@@ -75,6 +203,74 @@ struct ProxyLink {
     listener: pipewire::link::LinkListener,
 }
 
+#[allow(dead_code)]
+struct ProxyNode {
+    proxy: pipewire::node::Node,
+    listener: pipewire::node::NodeListener,
+}
+
+#[allow(dead_code)]
+struct ProxyPort {
+    proxy: pipewire::port::Port,
+    listener: pipewire::port::PortListener,
+}
+
+#[allow(dead_code)]
+struct ProxyMetadata {
+    proxy: pipewire::metadata::Metadata,
+    listener: pipewire::metadata::MetadataListener,
+}
+
+#[allow(dead_code)]
+struct ProxyDevice {
+    proxy: pipewire::device::Device,
+    listener: pipewire::device::DeviceListener,
+}
+
+/// Proxy of an object created by pw-viz, objects which don't linger are destroyed along with it
+#[allow(dead_code)]
+struct OwnedProxy {
+    // Declared first so it is removed before the proxy is destroyed
+    listener: pipewire::proxy::ProxyListener,
+    proxy: pipewire::proxy::Proxy,
+    /// Id of the global object, known once the proxy is bound
+    global_id: Rc<Cell<Option<u32>>>,
+}
+
+impl OwnedProxy {
+    fn new(proxy: impl ProxyT) -> Self {
+        Self::with_gone(proxy, || {})
+    }
+    /// `gone` is called if the object fails to be created or is removed
+    fn with_gone(proxy: impl ProxyT, gone: impl Fn() + 'static) -> Self {
+        let global_id = Rc::new(Cell::new(None));
+        let gone = Rc::new(gone);
+
+        let listener = proxy
+            .upcast_ref()
+            .add_listener_local()
+            .bound({
+                let global_id = global_id.clone();
+                move |id| global_id.set(Some(id))
+            })
+            .removed({
+                let gone = gone.clone();
+                move || gone()
+            })
+            .error(move |_, _, message| {
+                log::error!("Proxy error: {}", message);
+                gone()
+            })
+            .register();
+
+        Self {
+            listener,
+            proxy: proxy.upcast(),
+            global_id,
+        }
+    }
+}
+
 /// Pipewire main_loop runs on a separate thread, and notifies the UI thread of any changes using a mpsc channel
 /// thread_main is the entry point of this thread
 pub fn thread_main(
@@ -87,12 +283,23 @@ pub fn thread_main(
 
     let proxies = Rc::new(RefCell::new(Default::default()));
     let proxies_rm = proxies.clone();
+    let proxies_ui = proxies.clone();
 
     let registry = core.get_registry_rc()?;
     let registry_clone = registry.clone();
 
     let sender_rm = sender.clone();
 
+    // Virtual devices and links which are destroyed along with their proxy when pw-viz exits
+    let owned = Rc::new(RefCell::new(Vec::<OwnedProxy>::new()));
+    let owned_rm = owned.clone();
+    let owned_link = owned.clone();
+
+    // Re-routed links waiting for their replacement to be established
+    let replacements = Rc::new(RefCell::new(Replacements::default()));
+    let replacements_rm = replacements.clone();
+    let replacements_ui = replacements.clone();
+
     let state = Rc::new(RefCell::new(State::new()));
     let state_rm = state.clone();
     let state_rm_link = state.clone();
@@ -103,13 +310,33 @@ pub fn thread_main(
         .global({
             move |global| match global.type_ {
                 pipewire::types::ObjectType::Node => {
-                    handle_node(global, &state, &sender);
+                    handle_node(global, &state, &sender, &registry_clone, &proxies);
                 }
                 pipewire::types::ObjectType::Link => {
-                    handle_link(global, &state, &sender, &registry_clone, &proxies);
+                    handle_link(
+                        global,
+                        &state,
+                        &sender,
+                        &registry_clone,
+                        &proxies,
+                        &replacements,
+                        &owned_link,
+                    );
                 }
                 pipewire::types::ObjectType::Port => {
-                    handle_port(global, &state, &sender);
+                    handle_port(global, &state, &sender, &registry_clone, &proxies);
+                }
+                pipewire::types::ObjectType::Metadata => {
+                    handle_metadata(global, &state, &sender, &registry_clone, &proxies);
+                }
+                pipewire::types::ObjectType::Device => {
+                    handle_device(global, &state, &sender, &registry_clone, &proxies);
+                }
+                pipewire::types::ObjectType::Client => {
+                    handle_client(global, &state, &sender);
+                }
+                pipewire::types::ObjectType::Profiler => {
+                    handle_profiler(global, &state, &sender, &registry_clone, &proxies);
                 }
                 _ => {}
             }
@@ -129,12 +356,23 @@ pub fn thread_main(
                         node_id,
                         id,
                     },
+                    state::GlobalObject::Metadata { name } => {
+                        PipewireMessage::MetadataRemoved { name, id }
+                    }
+                    state::GlobalObject::Device => PipewireMessage::DeviceRemoved { id },
+                    state::GlobalObject::Client => PipewireMessage::ClientRemoved { id },
+                    state::GlobalObject::Profiler => PipewireMessage::ProfilerRemoved,
                 };
                 sender_rm
                     .send(message)
                     .expect("Failed to send pipewire message");
 
                 proxies_rm.borrow_mut().remove(&id);
+                // The object is gone, its proxy is of no use anymore
+                owned_rm
+                    .borrow_mut()
+                    .retain(|owned| owned.global_id.get() != Some(id));
+                replacements_rm.borrow_mut().global_removed(id);
             }
             None => {
                 log::warn!("Object with id: {} was never registered\n", id);
@@ -153,9 +391,76 @@ pub fn thread_main(
             UiMessage::RemoveLink(link_id) => {
                 remove_link(link_id, &state, &registry);
             }
-            UiMessage::AddLink { from_port, to_port } => {
-                add_link(&state, from_port, to_port, &core)
+            UiMessage::AddLink {
+                from_port,
+                to_port,
+                options,
+                origin,
+            } => {
+                if let Err(err) =
+                    add_link(&state, from_port, to_port, options, origin, &core, &owned)
+                {
+                    log::error!("Failed to add new link: {}", err);
+                }
+            }
+            UiMessage::ReplaceLink {
+                old,
+                from_port,
+                to_port,
+                options,
+                origin,
+            } => match create_link(&state, from_port, to_port, options, origin, &core) {
+                Ok(proxy) => replace_link(
+                    old,
+                    (from_port, to_port),
+                    options.linger,
+                    proxy,
+                    &owned,
+                    &replacements_ui,
+                ),
+                // The old link is kept
+                Err(err) => log::error!("Failed to re-route link {}: {}", old, err),
+            },
+            UiMessage::SetDefaultNode { kind, pw_name } => {
+                set_default_node(kind, &pw_name, &state, &proxies_ui)
             }
+            UiMessage::SetStreamTarget { node_id, target } => {
+                set_stream_target(node_id, target.as_deref(), &state, &proxies_ui)
+            }
+            UiMessage::SetMetadataProperty {
+                metadata,
+                subject,
+                key,
+                type_,
+                value,
+            } => set_metadata_property(
+                &metadata,
+                subject,
+                &key,
+                type_.as_deref(),
+                value.as_deref(),
+                &state,
+                &proxies_ui,
+            ),
+            UiMessage::ClearMetadata { metadata } => clear_metadata(&metadata, &state, &proxies_ui),
+            UiMessage::SetDeviceProfile { device_id, index } => set_device_param(
+                device_id,
+                ParamType::Profile,
+                &device::profile_param(index),
+                &proxies_ui,
+            ),
+            UiMessage::SetDeviceRoute {
+                device_id,
+                device,
+                index,
+            } => set_device_param(
+                device_id,
+                ParamType::Route,
+                &device::route_param(device, index),
+                &proxies_ui,
+            ),
+            UiMessage::DestroyNode(node_id) => destroy_node(node_id, &state, &registry),
+            UiMessage::CreateVirtualDevice(device) => create_virtual_device(&device, &core, &owned),
             UiMessage::Exit => mainloop_clone.quit(),
         }
     });
@@ -169,6 +474,8 @@ fn handle_node(
     node: &GlobalObject<&DictRef>,
     state: &Rc<RefCell<State>>,
     sender: &Rc<Sender<PipewireMessage>>,
+    registry: &RegistryRc,
+    proxies: &Rc<RefCell<Proxies>>,
 ) {
     let props = node
         .props
@@ -176,6 +483,10 @@ fn handle_node(
         .expect("Node object doesn't have properties");
 
     let description = props.get("node.description");
+    let pw_name = props.get("node.name").unwrap_or_default().to_string();
+    let media_class = props.get("media.class").map(|class| class.to_string());
+    let device_id = props.get("device.id").and_then(|id| id.parse().ok());
+    let client_id = props.get("client.id").and_then(|id| id.parse().ok());
 
     let name = props
         .get("node.nick")
@@ -204,11 +515,51 @@ fn handle_node(
     sender
         .send(PipewireMessage::NodeAdded {
             id: node.id,
-            name,
+            name: name.clone(),
+            pw_name,
             description,
+            media_class,
             media_type,
+            device_id,
+            client_id,
         })
         .expect("Failed to send pipewire message");
+
+    let proxy: pipewire::node::Node = registry.bind(node).expect("Failed to bind node proxy");
+    proxy.subscribe_params(&[ParamType::EnumFormat, ParamType::Format]);
+
+    let sender_info = sender.clone();
+    let sender = sender.clone();
+    let name_info = name.clone();
+    let id = node.id;
+
+    let listener = proxy
+        .add_listener_local()
+        .info(move |info| {
+            sender_info
+                .send(PipewireMessage::NodeSchedulingChanged {
+                    name: name_info.clone(),
+                    id,
+                    scheduling: Scheduling::from_info(info),
+                })
+                .expect("Failed to send pipewire message");
+        })
+        .param(move |_seq, param_id, index, _next, param| {
+            if let Some(update) = FormatUpdate::from_param(param_id, index, param) {
+                sender
+                    .send(PipewireMessage::NodeFormatChanged {
+                        name: name.clone(),
+                        id,
+                        update,
+                    })
+                    .expect("Failed to send pipewire message");
+            }
+        })
+        .register();
+
+    proxies
+        .borrow_mut()
+        .insert(node.id, Proxy::Node(ProxyNode { proxy, listener }));
 }
 
 fn handle_link(
@@ -217,11 +568,16 @@ fn handle_link(
     sender: &Rc<Sender<PipewireMessage>>,
     registry: &RegistryRc,
     proxies: &Rc<RefCell<Proxies>>,
+    replacements: &Rc<RefCell<Replacements>>,
+    owned: &Rc<RefCell<Vec<OwnedProxy>>>,
 ) {
     let proxy: pipewire::link::Link = registry.bind(link).expect("Failed to bind link proxy");
 
     let sender = sender.clone();
     let state = state.clone();
+    let registry = registry.clone();
+    let replacements = replacements.clone();
+    let owned = owned.clone();
 
     let listener = proxy
         .add_listener_local()
@@ -260,54 +616,156 @@ fn handle_link(
                         from_port,
                         to_port,
                         id,
+                        properties: LinkProperties::from_props(info.props()),
                     })
                     .expect("Failed to send pipewire message");
             }
+
+            let settled = replacements.borrow_mut().settle(id, &info.state());
+            if let Some((settle, replacement)) = settled {
+                settle_replacement(id, settle, replacement, &owned, &registry);
+            }
         })
         .register();
 
     proxies
         .borrow_mut()
-        .insert(link.id, ProxyLink { proxy, listener });
+        .insert(link.id, Proxy::Link(ProxyLink { proxy, listener }));
 }
 
-fn add_link(state: &Rc<RefCell<State>>, from_port: u32, to_port: u32, core: &Core) {
-    let state = state.borrow();
-    let from_port_ob = state
-        .get(from_port)
-        .expect(&format!("Port with id {} was never registered", from_port));
-    let from_node = *match from_port_ob {
-        state::GlobalObject::Port {
-            node_name: _,
-            node_id,
-            id: _,
-        } => node_id,
-        _ => unreachable!(),
+/// Once a re-routed link is established the link it replaces is removed,
+/// if it failed the new link is removed instead and the old one is kept
+fn settle_replacement(
+    link_id: u32,
+    settle: Settle,
+    replacement: Replacement,
+    owned: &RefCell<Vec<OwnedProxy>>,
+    registry: &RegistryRc,
+) {
+    let removed = match settle {
+        Settle::Established => {
+            // Lingering links stay around without their proxy
+            if replacement.linger {
+                owned
+                    .borrow_mut()
+                    .retain(|owned| owned.global_id.get() != Some(link_id));
+            }
+            replacement.old
+        }
+        Settle::Failed(err) => {
+            log::error!("Failed to re-route link {}: {}", replacement.old, err);
+            link_id
+        }
     };
 
-    let to_port_ob = state
-        .get(to_port)
-        .expect(&format!("Port with id {} was never registered", to_port));
-    let to_node = *match to_port_ob {
-        state::GlobalObject::Port {
-            node_name: _,
-            node_id,
-            id: _,
-        } => node_id,
-        _ => unreachable!(),
-    };
+    if let Err(err) = registry.destroy_global(removed).into_result() {
+        log::error!("SPA error: {}", err)
+    }
+}
 
-    core.create_object::<pipewire::link::Link>(
-        "link-factory",
-        &pipewire::properties::properties! {
-            "link.input.port" => to_port.to_string(),
-            "link.output.port" => from_port.to_string(),
-            "link.input.node" => to_node.to_string(),
-            "link.output.node"=> from_node.to_string(),
-            "object.linger" => "1"
+fn add_link(
+    state: &Rc<RefCell<State>>,
+    from_port: u32,
+    to_port: u32,
+    options: LinkOptions,
+    origin: LinkOrigin,
+    core: &Core,
+    owned: &RefCell<Vec<OwnedProxy>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let proxy = create_link(state, from_port, to_port, options, origin, core)?;
+
+    // Lingering links stay around without their proxy
+    if !options.linger {
+        owned.borrow_mut().push(OwnedProxy::new(proxy));
+    }
+
+    Ok(())
+}
+
+/// The proxy of the new link is kept until the link is established or fails,
+/// the old link is only removed once the new one is established
+fn replace_link(
+    old: u32,
+    ports: (u32, u32),
+    linger: bool,
+    proxy: pipewire::link::Link,
+    owned: &RefCell<Vec<OwnedProxy>>,
+    replacements: &Rc<RefCell<Replacements>>,
+) {
+    let proxy_id = proxy.upcast_ref().id();
+
+    let owned_proxy = OwnedProxy::with_gone(proxy, {
+        let replacements = replacements.clone();
+        move || {
+            // The old link is kept
+            if let Some(replacement) = replacements.borrow_mut().remove_proxy(proxy_id) {
+                log::error!("Failed to re-route link {}", replacement.old);
+            }
+        }
+    });
+
+    replacements.borrow_mut().insert(
+        proxy_id,
+        Replacement {
+            old,
+            ports,
+            link: owned_proxy.global_id.clone(),
+            linger,
         },
-    )
-    .expect("Failed to add new link");
+    );
+    owned.borrow_mut().push(owned_proxy);
+}
+
+fn create_link(
+    state: &Rc<RefCell<State>>,
+    from_port: u32,
+    to_port: u32,
+    options: LinkOptions,
+    origin: LinkOrigin,
+    core: &Core,
+) -> Result<pipewire::link::Link, Box<dyn std::error::Error>> {
+    let state = state.borrow();
+    // The ports may have been removed since the ui sent the message
+    let from_node = port_node(&state, from_port)?;
+    let to_node = port_node(&state, to_port)?;
+
+    let proxy = core.create_object::<pipewire::link::Link>(
+        "link-factory",
+        &options.properties(origin, from_node, from_port, to_node, to_port),
+    )?;
+
+    Ok(proxy)
+}
+
+/// Id of the node owning the port
+fn port_node(state: &State, port_id: u32) -> Result<u32, String> {
+    match state.get(port_id) {
+        Some(state::GlobalObject::Port { node_id, .. }) => Ok(*node_id),
+        Some(_) => Err(format!("Object with id {} isn't a port", port_id)),
+        None => Err(format!("Port with id {} was never registered", port_id)),
+    }
+}
+
+fn create_virtual_device(device: &VirtualDevice, core: &Core, owned: &RefCell<Vec<OwnedProxy>>) {
+    match core.create_object::<pipewire::node::Node>("adapter", &device.properties()) {
+        Ok(proxy) => {
+            // Lingering devices stay around without their proxy
+            if !device.linger {
+                owned.borrow_mut().push(OwnedProxy::new(proxy));
+            }
+        }
+        Err(err) => log::error!("Failed to create virtual device {}: {}", device.name, err),
+    }
+}
+
+fn destroy_node(node_id: u32, state: &Rc<RefCell<State>>, registry: &RegistryRc) {
+    if let Some(state::GlobalObject::Node { .. }) = state.borrow().get(node_id) {
+        if let Err(err) = registry.destroy_global(node_id).into_result() {
+            log::error!("SPA error: {}", err)
+        }
+    } else {
+        log::warn!("Tried to destroy unregistered node with id: {}", node_id);
+    }
 }
 
 fn remove_link(link_id: u32, state: &Rc<RefCell<State>>, registry: &RegistryRc) {
@@ -324,6 +782,8 @@ fn handle_port(
     port: &GlobalObject<&DictRef>,
     state: &Rc<RefCell<State>>,
     sender: &Rc<Sender<PipewireMessage>>,
+    registry: &RegistryRc,
+    proxies: &Rc<RefCell<Proxies>>,
 ) {
     let props = port
         .props
@@ -357,6 +817,32 @@ fn handle_port(
         _ => PortType::Unknown,
     };
 
+    let channel = props
+        .get("audio.channel")
+        .map(|channel| channel.to_string());
+
+    // format.dsp looks like "32 bit float mono audio" or "8 bit raw midi"
+    let media_type = props
+        .get("format.dsp")
+        .and_then(|format| {
+            if format.contains("audio") {
+                Some(MediaType::Audio)
+            } else if format.contains("video") {
+                Some(MediaType::Video)
+            } else if format.contains("midi") {
+                Some(MediaType::Midi)
+            } else {
+                None
+            }
+        })
+        .or_else(|| channel.as_ref().map(|_| MediaType::Audio));
+
+    let flags = PortFlags {
+        monitor: props.get("port.monitor") == Some("true"),
+        physical: props.get("port.physical") == Some("true"),
+        terminal: props.get("port.terminal") == Some("true"),
+    };
+
     state.add(
         port.id,
         state::GlobalObject::Port {
@@ -368,11 +854,363 @@ fn handle_port(
 
     sender
         .send(PipewireMessage::PortAdded {
-            node_name,
+            node_name: node_name.clone(),
             node_id,
             id: port.id,
             name,
             port_type,
+            media_type,
+            channel,
+            flags,
+        })
+        .expect("Failed to send pipewire message");
+
+    let proxy: pipewire::port::Port = registry.bind(port).expect("Failed to bind port proxy");
+    proxy.subscribe_params(&[ParamType::EnumFormat, ParamType::Format]);
+
+    let sender = sender.clone();
+    let id = port.id;
+
+    let listener = proxy
+        .add_listener_local()
+        .param(move |_seq, param_id, index, _next, param| {
+            if let Some(update) = FormatUpdate::from_param(param_id, index, param) {
+                sender
+                    .send(PipewireMessage::PortFormatChanged {
+                        node_name: node_name.clone(),
+                        node_id,
+                        id,
+                        update,
+                    })
+                    .expect("Failed to send pipewire message");
+            }
+        })
+        .register();
+
+    proxies
+        .borrow_mut()
+        .insert(port.id, Proxy::Port(ProxyPort { proxy, listener }));
+}
+
+fn handle_metadata(
+    metadata: &GlobalObject<&DictRef>,
+    state: &Rc<RefCell<State>>,
+    sender: &Rc<Sender<PipewireMessage>>,
+    registry: &RegistryRc,
+    proxies: &Rc<RefCell<Proxies>>,
+) {
+    let name = metadata
+        .props
+        .as_ref()
+        .and_then(|props| props.get("metadata.name"))
+        .unwrap_or_default()
+        .to_string();
+
+    let proxy: pipewire::metadata::Metadata = registry
+        .bind(metadata)
+        .expect("Failed to bind metadata proxy");
+
+    sender
+        .send(PipewireMessage::MetadataAdded {
+            name: name.clone(),
+            id: metadata.id,
+        })
+        .expect("Failed to send pipewire message");
+
+    let sender = sender.clone();
+    let metadata_name = name.clone();
+
+    let listener = proxy
+        .add_listener_local()
+        .property(move |subject, key, type_, value| {
+            if metadata_name == metadata::DEFAULT_METADATA {
+                default_property_changed(&sender, subject, key, value);
+            }
+
+            sender
+                .send(PipewireMessage::MetadataPropertyChanged {
+                    metadata: metadata_name.clone(),
+                    subject,
+                    key: key.map(str::to_string),
+                    type_: type_.map(str::to_string),
+                    value: value.map(str::to_string),
+                })
+                .expect("Failed to send pipewire message");
+
+            0
+        })
+        .register();
+
+    state
+        .borrow_mut()
+        .add(metadata.id, state::GlobalObject::Metadata { name });
+
+    proxies.borrow_mut().insert(
+        metadata.id,
+        Proxy::Metadata(ProxyMetadata { proxy, listener }),
+    );
+}
+
+fn default_property_changed(
+    sender: &Sender<PipewireMessage>,
+    subject: u32,
+    key: Option<&str>,
+    value: Option<&str>,
+) {
+    // Subject 0 holds global settings, other subjects are object ids
+    if subject != 0 {
+        if key.map_or(true, |key| key == metadata::TARGET_OBJECT) {
+            sender
+                .send(PipewireMessage::StreamTargetChanged {
+                    id: subject,
+                    target: value.filter(|_| key.is_some()).map(str::to_string),
+                })
+                .expect("Failed to send pipewire message");
+        }
+
+        return;
+    }
+
+    match key {
+        Some(key) => {
+            if let Some((kind, configured)) = DefaultKind::from_key(key) {
+                sender
+                    .send(PipewireMessage::DefaultNodeChanged {
+                        kind,
+                        configured,
+                        pw_name: value.and_then(metadata::parse_name),
+                    })
+                    .expect("Failed to send pipewire message");
+            }
+        }
+        // All properties were cleared
+        None => {
+            for kind in [DefaultKind::Sink, DefaultKind::Source] {
+                for configured in [false, true] {
+                    sender
+                        .send(PipewireMessage::DefaultNodeChanged {
+                            kind,
+                            configured,
+                            pw_name: None,
+                        })
+                        .expect("Failed to send pipewire message");
+                }
+            }
+        }
+    }
+}
+
+fn handle_device(
+    device: &GlobalObject<&DictRef>,
+    state: &Rc<RefCell<State>>,
+    sender: &Rc<Sender<PipewireMessage>>,
+    registry: &RegistryRc,
+    proxies: &Rc<RefCell<Proxies>>,
+) {
+    let props = device
+        .props
+        .as_ref()
+        .expect("Device object doesn't have properties");
+
+    let pw_name = props.get("device.name").unwrap_or_default().to_string();
+    let name = props
+        .get("device.description")
+        .or_else(|| props.get("device.nick"))
+        .unwrap_or(pw_name.as_str())
+        .to_string();
+
+    state
+        .borrow_mut()
+        .add(device.id, state::GlobalObject::Device);
+
+    sender
+        .send(PipewireMessage::DeviceAdded {
+            id: device.id,
+            name,
+            pw_name,
+        })
+        .expect("Failed to send pipewire message");
+
+    let proxy: pipewire::device::Device =
+        registry.bind(device).expect("Failed to bind device proxy");
+    proxy.subscribe_params(&[
+        ParamType::EnumProfile,
+        ParamType::Profile,
+        ParamType::EnumRoute,
+        ParamType::Route,
+    ]);
+
+    let sender = sender.clone();
+    let id = device.id;
+
+    let listener = proxy
+        .add_listener_local()
+        .param(move |_seq, param_id, index, _next, param| {
+            if let Some(update) = DeviceUpdate::from_param(param_id, index, param) {
+                sender
+                    .send(PipewireMessage::DeviceChanged { id, update })
+                    .expect("Failed to send pipewire message");
+            }
+        })
+        .register();
+
+    proxies
+        .borrow_mut()
+        .insert(device.id, Proxy::Device(ProxyDevice { proxy, listener }));
+}
+
+fn handle_client(
+    client: &GlobalObject<&DictRef>,
+    state: &Rc<RefCell<State>>,
+    sender: &Rc<Sender<PipewireMessage>>,
+) {
+    let props = match client.props.as_ref() {
+        Some(props) => props,
+        None => return,
+    };
+
+    let binary = props
+        .get("application.process.binary")
+        .map(|binary| binary.to_string());
+    let pid = props
+        .get("application.process.id")
+        .and_then(|pid| pid.parse().ok());
+
+    let name = props
+        .get("application.name")
+        .map(|name| name.to_string())
+        .or_else(|| binary.clone())
+        .unwrap_or_else(|| format!("Client {}", client.id));
+
+    state
+        .borrow_mut()
+        .add(client.id, state::GlobalObject::Client);
+
+    sender
+        .send(PipewireMessage::ClientAdded {
+            id: client.id,
+            name,
+            pid,
+            binary,
         })
         .expect("Failed to send pipewire message");
 }
+
+fn handle_profiler(
+    profiler: &GlobalObject<&DictRef>,
+    state: &Rc<RefCell<State>>,
+    sender: &Rc<Sender<PipewireMessage>>,
+    registry: &RegistryRc,
+    proxies: &Rc<RefCell<Proxies>>,
+) {
+    let sender = sender.clone();
+
+    let proxy = Profiler::bind(registry, profiler, move |sample| {
+        sender
+            .send(PipewireMessage::Profile(sample))
+            .expect("Failed to send pipewire message");
+    });
+
+    match proxy {
+        Some(proxy) => {
+            state
+                .borrow_mut()
+                .add(profiler.id, state::GlobalObject::Profiler);
+
+            proxies
+                .borrow_mut()
+                .insert(profiler.id, Proxy::Profiler(proxy));
+        }
+        None => log::error!("Failed to bind profiler with id: {}", profiler.id),
+    }
+}
+
+fn set_device_param(
+    device_id: u32,
+    param_type: ParamType,
+    param: &[u8],
+    proxies: &Rc<RefCell<Proxies>>,
+) {
+    let param = Pod::from_bytes(param).expect("Device param is not a valid pod");
+
+    match proxies.borrow().get(&device_id) {
+        Some(Proxy::Device(device)) => device.proxy.set_param(param_type, 0, param),
+        _ => log::error!("Device with id: {} was never registered", device_id),
+    }
+}
+
+fn set_default_node(
+    kind: DefaultKind,
+    pw_name: &str,
+    state: &Rc<RefCell<State>>,
+    proxies: &Rc<RefCell<Proxies>>,
+) {
+    set_metadata_property(
+        metadata::DEFAULT_METADATA,
+        0,
+        kind.configured_key(),
+        Some("Spa:String:JSON"),
+        Some(&metadata::format_name(pw_name)),
+        state,
+        proxies,
+    );
+}
+
+/// Makes the session manager link the stream to the target node, or to the default one if `target` is `None`
+fn set_stream_target(
+    node_id: u32,
+    target: Option<&str>,
+    state: &Rc<RefCell<State>>,
+    proxies: &Rc<RefCell<Proxies>>,
+) {
+    set_metadata_property(
+        metadata::DEFAULT_METADATA,
+        node_id,
+        metadata::TARGET_OBJECT,
+        None,
+        target,
+        state,
+        proxies,
+    );
+}
+
+/// Sets a property of the metadata object with the given metadata.name, a `None` value removes the property
+fn set_metadata_property(
+    name: &str,
+    subject: u32,
+    key: &str,
+    type_: Option<&str>,
+    value: Option<&str>,
+    state: &Rc<RefCell<State>>,
+    proxies: &Rc<RefCell<Proxies>>,
+) {
+    let id = match state.borrow().find_metadata(name) {
+        Some(id) => id,
+        None => {
+            log::warn!(
+                "No metadata object named {}, is a session manager running?",
+                name
+            );
+            return;
+        }
+    };
+
+    if let Some(Proxy::Metadata(metadata)) = proxies.borrow().get(&id) {
+        metadata.proxy.set_property(subject, key, type_, value);
+    }
+}
+
+/// Removes all properties of the metadata object with the given metadata.name
+fn clear_metadata(name: &str, state: &Rc<RefCell<State>>, proxies: &Rc<RefCell<Proxies>>) {
+    let id = match state.borrow().find_metadata(name) {
+        Some(id) => id,
+        None => {
+            log::warn!("No metadata object named {}", name);
+            return;
+        }
+    };
+
+    if let Some(Proxy::Metadata(metadata)) = proxies.borrow().get(&id) {
+        metadata.proxy.clear();
+    }
+}
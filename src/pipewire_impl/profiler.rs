@@ -0,0 +1,365 @@
+use std::{
+    ffi::{c_char, c_void},
+    mem,
+    pin::Pin,
+};
+
+use pipewire::{
+    registry::{GlobalObject, Registry},
+    spa::{
+        pod::{deserialize::PodDeserializer, Pod, Value},
+        sys as spa_sys,
+        utils::{dict::DictRef, Fraction},
+    },
+    sys as pw_sys,
+};
+
+const PROFILER_TYPE: &[u8] = b"PipeWire:Interface:Profiler\0";
+const PROFILER_VERSION: u32 = 3;
+
+/// Load of the whole graph, averaged over different periods
+#[derive(Debug, Clone, Copy)]
+pub struct ProfilerInfo {
+    /// Fast, medium and slow averages
+    pub cpu_load: [f32; 3],
+    pub xruns: i32,
+}
+
+/// Timing of a node during the last cycle of its driver, in nanoseconds
+#[derive(Debug, Clone)]
+pub struct NodeTiming {
+    pub id: u32,
+    pub name: String,
+    /// Time between the node being signaled and it waking up
+    pub wait: i64,
+    /// Time the node spent processing
+    pub busy: i64,
+    pub xruns: Option<i32>,
+}
+
+/// One cycle of a driver and its followers
+#[derive(Debug, Clone)]
+pub struct DriverProfile {
+    pub driver: NodeTiming,
+    pub quantum: i64,
+    pub rate: u32,
+    /// Fraction of the cycle spent processing the whole group, above 1.0 the cycle overran
+    pub dsp_load: f32,
+    pub followers: Vec<NodeTiming>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ProfilerSample {
+    pub info: Option<ProfilerInfo>,
+    pub drivers: Vec<DriverProfile>,
+}
+
+#[repr(C)]
+struct ProfilerEvents {
+    version: u32,
+    profile: Option<unsafe extern "C" fn(data: *mut c_void, pod: *const spa_sys::spa_pod)>,
+}
+
+type ProfileCallback = Box<dyn Fn(ProfilerSample)>;
+
+/// Proxy of the profiler object, pipewire-rs doesn't wrap this interface so it is bound using the C api
+pub struct Profiler {
+    proxy: *mut pw_sys::pw_proxy,
+    _listener: Pin<Box<spa_sys::spa_hook>>,
+    _events: Box<ProfilerEvents>,
+    _callback: Box<ProfileCallback>,
+}
+
+impl Profiler {
+    pub fn bind<F>(
+        registry: &Registry,
+        global: &GlobalObject<&DictRef>,
+        callback: F,
+    ) -> Option<Self>
+    where
+        F: Fn(ProfilerSample) + 'static,
+    {
+        let proxy = unsafe {
+            pipewire::spa::spa_interface_call_method!(
+                registry.as_raw_ptr(),
+                pw_sys::pw_registry_methods,
+                bind,
+                global.id,
+                PROFILER_TYPE.as_ptr() as *const c_char,
+                PROFILER_VERSION,
+                0
+            )
+        } as *mut pw_sys::pw_proxy;
+
+        if proxy.is_null() {
+            return None;
+        }
+
+        let callback: Box<ProfileCallback> = Box::new(Box::new(callback));
+        let events = Box::new(ProfilerEvents {
+            version: 0,
+            profile: Some(on_profile),
+        });
+        let mut listener = Box::pin(unsafe { mem::zeroed::<spa_sys::spa_hook>() });
+
+        unsafe {
+            pw_sys::pw_proxy_add_object_listener(
+                proxy,
+                listener.as_mut().get_unchecked_mut(),
+                events.as_ref() as *const ProfilerEvents as *const c_void,
+                callback.as_ref() as *const ProfileCallback as *mut c_void,
+            );
+        }
+
+        Some(Self {
+            proxy,
+            _listener: listener,
+            _events: events,
+            _callback: callback,
+        })
+    }
+}
+
+impl Drop for Profiler {
+    fn drop(&mut self) {
+        // Also removes the listener
+        unsafe { pw_sys::pw_proxy_destroy(self.proxy) }
+    }
+}
+
+unsafe extern "C" fn on_profile(data: *mut c_void, pod: *const spa_sys::spa_pod) {
+    if data.is_null() || pod.is_null() {
+        return;
+    }
+
+    let callback = &*(data as *const ProfileCallback);
+    let pod = Pod::from_raw(pod);
+
+    if let Some(sample) = parse_profile(pod) {
+        callback(sample);
+    }
+}
+
+/// The profile is a struct of profiler objects, one per driver that completed a cycle
+fn parse_profile(pod: &Pod) -> Option<ProfilerSample> {
+    let objects = match PodDeserializer::deserialize_any_from(pod.as_bytes()) {
+        Ok((_, Value::Struct(objects))) => objects,
+        _ => return None,
+    };
+
+    let mut sample = ProfilerSample::default();
+
+    for object in objects {
+        let object = match object {
+            Value::Object(object) if object.type_ == spa_sys::SPA_TYPE_OBJECT_Profiler => object,
+            _ => continue,
+        };
+
+        let mut clock = None;
+        let mut driver = None;
+        let mut followers = Vec::new();
+
+        for property in object.properties {
+            let fields = match property.value {
+                Value::Struct(fields) => fields,
+                _ => continue,
+            };
+
+            match property.key {
+                spa_sys::SPA_PROFILER_info => sample.info = parse_info(&fields),
+                spa_sys::SPA_PROFILER_clock => clock = parse_clock(&fields),
+                spa_sys::SPA_PROFILER_driverBlock => driver = parse_block(&fields),
+                spa_sys::SPA_PROFILER_followerBlock => {
+                    followers.extend(parse_block(&fields).map(|(timing, _)| timing))
+                }
+                _ => {}
+            }
+        }
+
+        if let (Some((quantum, rate)), Some((driver, (signal, finish)))) = (clock, driver) {
+            let period = quantum as f64 * 1e9 / rate.max(1) as f64;
+
+            sample.drivers.push(DriverProfile {
+                driver,
+                quantum,
+                rate,
+                dsp_load: ((finish - signal) as f64 / period) as f32,
+                followers,
+            });
+        }
+    }
+
+    Some(sample)
+}
+
+fn parse_info(fields: &[Value]) -> Option<ProfilerInfo> {
+    match fields {
+        [Value::Long(_), Value::Float(fast), Value::Float(medium), Value::Float(slow), Value::Int(xruns), ..] => {
+            Some(ProfilerInfo {
+                cpu_load: [*fast, *medium, *slow],
+                xruns: *xruns,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Returns the quantum and the rate of the driver
+fn parse_clock(fields: &[Value]) -> Option<(i64, u32)> {
+    match fields {
+        [Value::Int(_), Value::Int(_), Value::String(_), Value::Long(_), Value::Fraction(Fraction { denom, .. }), Value::Long(_), Value::Long(duration), ..] => {
+            Some((*duration, *denom))
+        }
+        _ => None,
+    }
+}
+
+/// Returns the timing of a node, along with the times it was signaled and it finished
+fn parse_block(fields: &[Value]) -> Option<(NodeTiming, (i64, i64))> {
+    match fields {
+        [Value::Int(id), Value::String(name), Value::Long(_prev_signal), Value::Long(signal), Value::Long(awake), Value::Long(finish), Value::Int(_status), Value::Fraction(_latency), rest @ ..] =>
+        {
+            let xruns = match rest.first() {
+                Some(Value::Int(xruns)) => Some(*xruns),
+                _ => None,
+            };
+
+            let timing = NodeTiming {
+                id: *id as u32,
+                name: name.clone(),
+                wait: (awake - signal).max(0),
+                busy: (finish - awake).max(0),
+                xruns,
+            };
+
+            Some((timing, (*signal, *finish)))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pipewire::spa::pod::{serialize::PodSerializer, Object, Property};
+    use std::io::Cursor;
+
+    fn info() -> Vec<Value> {
+        vec![
+            Value::Long(1),
+            Value::Float(0.25),
+            Value::Float(0.2),
+            Value::Float(0.1),
+            Value::Int(3),
+        ]
+    }
+
+    /// A driver running at 1024/48000
+    fn clock() -> Vec<Value> {
+        vec![
+            Value::Int(0),
+            Value::Int(40),
+            Value::String("alsa".to_string()),
+            Value::Long(0),
+            Value::Fraction(Fraction {
+                num: 1,
+                denom: 48000,
+            }),
+            Value::Long(0),
+            Value::Long(1024),
+        ]
+    }
+
+    fn block(id: i32, signal: i64, awake: i64, finish: i64, xruns: Option<i32>) -> Vec<Value> {
+        let mut fields = vec![
+            Value::Int(id),
+            Value::String(format!("node {}", id)),
+            Value::Long(0),
+            Value::Long(signal),
+            Value::Long(awake),
+            Value::Long(finish),
+            Value::Int(0),
+            Value::Fraction(Fraction {
+                num: 1024,
+                denom: 48000,
+            }),
+        ];
+        fields.extend(xruns.map(Value::Int));
+        fields
+    }
+
+    #[test]
+    fn info_fields() {
+        let info = parse_info(&info()).unwrap();
+
+        assert_eq!(info.cpu_load, [0.25, 0.2, 0.1]);
+        assert_eq!(info.xruns, 3);
+        assert!(parse_info(&info()[..4]).is_none());
+    }
+
+    #[test]
+    fn clock_fields() {
+        assert_eq!(parse_clock(&clock()), Some((1024, 48000)));
+        assert_eq!(parse_clock(&info()), None);
+    }
+
+    #[test]
+    fn block_fields() {
+        let (timing, (signal, finish)) = parse_block(&block(40, 100, 150, 400, Some(2))).unwrap();
+
+        assert_eq!((timing.id, timing.name.as_str()), (40, "node 40"));
+        assert_eq!((timing.wait, timing.busy), (50, 250));
+        assert_eq!(timing.xruns, Some(2));
+        assert_eq!((signal, finish), (100, 400));
+
+        // Older versions of the profiler don't report xruns per node
+        let (timing, _) = parse_block(&block(40, 100, 150, 400, None)).unwrap();
+        assert_eq!(timing.xruns, None);
+
+        // Nodes woken up before being signaled don't wait
+        let (timing, _) = parse_block(&block(40, 100, 50, 400, None)).unwrap();
+        assert_eq!(timing.wait, 0);
+
+        assert!(parse_block(&clock()).is_none());
+    }
+
+    #[test]
+    fn profile() {
+        let property = |key, fields| Property::new(key, Value::Struct(fields));
+        // Half of the 1024/48000 cycle
+        let finish = 1_000 + 10_666_667;
+
+        let profile = Value::Struct(vec![Value::Object(Object {
+            type_: spa_sys::SPA_TYPE_OBJECT_Profiler,
+            id: 0,
+            properties: vec![
+                property(spa_sys::SPA_PROFILER_info, info()),
+                property(spa_sys::SPA_PROFILER_clock, clock()),
+                property(
+                    spa_sys::SPA_PROFILER_driverBlock,
+                    block(40, 1_000, 1_000, finish, None),
+                ),
+                property(
+                    spa_sys::SPA_PROFILER_followerBlock,
+                    block(41, 2_000, 3_000, 5_000, None),
+                ),
+            ],
+        })]);
+        let bytes = PodSerializer::serialize(Cursor::new(Vec::new()), &profile)
+            .expect("Failed to serialize profile")
+            .0
+            .into_inner();
+
+        let sample = parse_profile(Pod::from_bytes(&bytes).unwrap()).unwrap();
+        assert_eq!(sample.info.unwrap().xruns, 3);
+
+        let driver = &sample.drivers[0];
+        assert_eq!(
+            (driver.driver.id, driver.quantum, driver.rate),
+            (40, 1024, 48000)
+        );
+        assert!((driver.dsp_load - 0.5).abs() < 0.001);
+        assert_eq!(driver.followers.len(), 1);
+        assert_eq!(driver.followers[0].id, 41);
+    }
+}
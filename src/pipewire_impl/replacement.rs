@@ -0,0 +1,167 @@
+use pipewire::link::LinkState;
+use std::{cell::Cell, collections::HashMap, rc::Rc};
+
+/// A re-routed link waiting for the link replacing it to be established
+#[derive(Debug)]
+pub struct Replacement {
+    /// Link being replaced
+    pub old: u32,
+    /// Output and input port of the new link
+    pub ports: (u32, u32),
+    /// Global id of the new link, known once its proxy is bound
+    pub link: Rc<Cell<Option<u32>>>,
+    /// Lingering links don't keep their proxy once established
+    pub linger: bool,
+}
+
+impl Replacement {
+    fn involves(&self, id: u32) -> bool {
+        self.old == id || self.ports.0 == id || self.ports.1 == id || self.link.get() == Some(id)
+    }
+}
+
+/// How a replacement ends once the new link leaves its setup states
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Settle {
+    /// The old link can be removed
+    Established,
+    /// The new link is removed, the old one is kept
+    Failed(String),
+}
+
+impl Settle {
+    /// None while the link is still being set up, it can still fail while allocating buffers
+    pub fn from_state(state: &LinkState) -> Option<Self> {
+        match state {
+            LinkState::Paused | LinkState::Active => Some(Self::Established),
+            LinkState::Error(err) => Some(Self::Failed(err.to_string())),
+            _ => None,
+        }
+    }
+}
+
+/// Pending replacements, by the local id of the proxy of the new link
+#[derive(Debug, Default)]
+pub struct Replacements(HashMap<u32, Replacement>);
+
+impl Replacements {
+    pub fn insert(&mut self, proxy_id: u32, replacement: Replacement) {
+        self.0.insert(proxy_id, replacement);
+    }
+    /// Drops the replacement whose new link failed or was removed before being established
+    pub fn remove_proxy(&mut self, proxy_id: u32) -> Option<Replacement> {
+        self.0.remove(&proxy_id)
+    }
+    /// Takes the replacement waiting on the link once its state settles it
+    pub fn settle(&mut self, link_id: u32, state: &LinkState) -> Option<(Settle, Replacement)> {
+        let proxy_id = self
+            .0
+            .iter()
+            .find(|(_, replacement)| replacement.link.get() == Some(link_id))
+            .map(|(&proxy_id, _)| proxy_id)?;
+
+        let settle = Settle::from_state(state)?;
+
+        self.0
+            .remove(&proxy_id)
+            .map(|replacement| (settle, replacement))
+    }
+    /// Drops the replacements involving a removed global, either of the links or their ports
+    pub fn global_removed(&mut self, id: u32) {
+        self.0.retain(|_, replacement| !replacement.involves(id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD: u32 = 10;
+    const NEW: u32 = 20;
+    const PROXY: u32 = 3;
+
+    fn pending(bound: Option<u32>) -> Replacements {
+        let mut replacements = Replacements::default();
+        replacements.insert(
+            PROXY,
+            Replacement {
+                old: OLD,
+                ports: (30, 31),
+                link: Rc::new(Cell::new(bound)),
+                linger: false,
+            },
+        );
+        replacements
+    }
+
+    #[test]
+    fn setup_states_keep_waiting() {
+        let mut replacements = pending(Some(NEW));
+
+        for state in [
+            LinkState::Init,
+            LinkState::Negotiating,
+            LinkState::Allocating,
+            LinkState::Unlinked,
+        ] {
+            assert!(replacements.settle(NEW, &state).is_none());
+        }
+        assert_eq!(replacements.0.len(), 1);
+    }
+
+    #[test]
+    fn paused_and_active_establish() {
+        for state in [LinkState::Paused, LinkState::Active] {
+            let mut replacements = pending(Some(NEW));
+
+            let (settle, replacement) = replacements.settle(NEW, &state).unwrap();
+            assert_eq!(settle, Settle::Established);
+            assert_eq!(replacement.old, OLD);
+            assert_eq!(replacements.0.len(), 0);
+        }
+    }
+
+    #[test]
+    fn error_fails() {
+        let mut replacements = pending(Some(NEW));
+
+        let (settle, _) = replacements
+            .settle(NEW, &LinkState::Error("no format"))
+            .unwrap();
+        assert_eq!(settle, Settle::Failed("no format".to_string()));
+        assert_eq!(replacements.0.len(), 0);
+    }
+
+    #[test]
+    fn other_and_unbound_links_are_ignored() {
+        let mut replacements = pending(Some(NEW));
+        assert!(replacements.settle(OLD, &LinkState::Active).is_none());
+
+        let mut replacements = pending(None);
+        assert!(replacements.settle(NEW, &LinkState::Active).is_none());
+        assert_eq!(replacements.0.len(), 1);
+    }
+
+    #[test]
+    fn proxy_errors_drop_the_replacement() {
+        let mut replacements = pending(Some(NEW));
+
+        assert!(replacements.remove_proxy(PROXY + 1).is_none());
+        assert_eq!(replacements.remove_proxy(PROXY).unwrap().old, OLD);
+        assert!(replacements.settle(NEW, &LinkState::Active).is_none());
+    }
+
+    #[test]
+    fn removed_globals_drop_the_replacement() {
+        for id in [OLD, NEW, 30, 31] {
+            let mut replacements = pending(Some(NEW));
+
+            replacements.global_removed(id);
+            assert_eq!(replacements.0.len(), 0);
+        }
+
+        let mut replacements = pending(Some(NEW));
+        replacements.global_removed(40);
+        assert_eq!(replacements.0.len(), 1);
+    }
+}
@@ -0,0 +1,53 @@
+use pipewire::node::NodeInfoRef;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NodeState {
+    Creating,
+    Suspended,
+    Idle,
+    Running,
+    Error(String),
+}
+
+/// How a node is scheduled, nodes following the same driver are processed together using its clock
+#[derive(Debug, Clone)]
+pub struct Scheduling {
+    /// Pipewire id of the driver of this node, its own id if the node is a driver
+    pub driver_id: Option<u32>,
+    pub state: NodeState,
+    /// Quantum and rate requested by the node, the driver decides on the actual ones
+    pub latency: Option<(u32, u32)>,
+}
+
+impl Scheduling {
+    pub fn from_info(info: &NodeInfoRef) -> Self {
+        let state = match info.state() {
+            pipewire::node::NodeState::Creating => NodeState::Creating,
+            pipewire::node::NodeState::Suspended => NodeState::Suspended,
+            pipewire::node::NodeState::Idle => NodeState::Idle,
+            pipewire::node::NodeState::Running => NodeState::Running,
+            pipewire::node::NodeState::Error(error) => NodeState::Error(error.to_string()),
+        };
+
+        let props = info.props();
+        let driver_id = props
+            .and_then(|props| props.get("node.driver-id"))
+            .and_then(|id| id.parse().ok());
+        let latency = props
+            .and_then(|props| props.get("node.latency"))
+            .and_then(parse_latency);
+
+        Self {
+            driver_id,
+            state,
+            latency,
+        }
+    }
+}
+
+/// Latencies are written as quantum/rate, like node.latency = "1024/48000"
+fn parse_latency(latency: &str) -> Option<(u32, u32)> {
+    let (quantum, rate) = latency.split_once('/')?;
+
+    Some((quantum.trim().parse().ok()?, rate.trim().parse().ok()?))
+}
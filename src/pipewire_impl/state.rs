@@ -10,6 +10,12 @@ pub enum GlobalObject {
         node_id: u32,
         id: u32,
     },
+    Metadata {
+        name: String,
+    },
+    Device,
+    Client,
+    Profiler,
 }
 
 /// For internal state tracking, this has to be done because pipewire only provides ids of the objects it removes,
@@ -33,4 +39,13 @@ impl State {
     pub fn remove(&mut self, id: u32) -> Option<GlobalObject> {
         self.objects.remove(&id)
     }
+    /// Looks up the id of a metadata object using its metadata.name
+    pub fn find_metadata(&self, name: &str) -> Option<u32> {
+        self.objects.iter().find_map(|(id, object)| match object {
+            GlobalObject::Metadata {
+                name: metadata_name,
+            } if metadata_name == name => Some(*id),
+            _ => None,
+        })
+    }
 }
@@ -0,0 +1,51 @@
+use pipewire::properties::{properties, PropertiesBox};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum VirtualDeviceKind {
+    Sink,
+    Source,
+}
+
+/// A null sink or source, used to route audio between applications
+#[derive(Debug, Clone)]
+pub struct VirtualDevice {
+    pub name: String,
+    pub kind: VirtualDeviceKind,
+    /// Channel positions, like FL and FR, one per channel
+    pub positions: Vec<String>,
+    /// Whether the device should outlive pw-viz
+    pub linger: bool,
+}
+
+impl VirtualDevice {
+    /// node.name has to be usable from the command line
+    pub fn node_name(&self) -> String {
+        self.name
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() {
+                    c.to_ascii_lowercase()
+                } else {
+                    '_'
+                }
+            })
+            .collect()
+    }
+    pub fn properties(&self) -> PropertiesBox {
+        let media_class = match self.kind {
+            VirtualDeviceKind::Sink => "Audio/Sink",
+            VirtualDeviceKind::Source => "Audio/Source/Virtual",
+        };
+
+        properties! {
+            "factory.name" => "support.null-audio-sink",
+            "node.name" => self.node_name(),
+            "node.description" => self.name.clone(),
+            "media.class" => media_class,
+            "audio.channels" => self.positions.len().to_string(),
+            "audio.position" => self.positions.join(","),
+            "monitor.channel-volumes" => "true",
+            "object.linger" => if self.linger { "true" } else { "false" }
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Names given by the user to nodes and ports, saved with the other settings
+///
+/// Nodes are keyed by node.name and ports by node.name:port.name, like pw-link does,
+/// so aliases survive restarts of pw-viz and of the nodes themselves
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Aliases {
+    nodes: HashMap<String, String>,
+    ports: HashMap<String, String>,
+}
+
+fn port_key(pw_name: &str, port_name: &str) -> String {
+    format!("{}:{}", pw_name, port_name)
+}
+
+/// Inserts the alias, or removes the entry if it is `None`
+fn set(map: &mut HashMap<String, String>, key: String, alias: Option<String>) {
+    match alias {
+        Some(alias) => {
+            map.insert(key, alias);
+        }
+        None => {
+            map.remove(&key);
+        }
+    }
+}
+
+impl Aliases {
+    pub fn node(&self, pw_name: &str) -> Option<&str> {
+        self.nodes.get(pw_name).map(String::as_str)
+    }
+    pub fn set_node(&mut self, pw_name: &str, alias: Option<String>) {
+        set(&mut self.nodes, pw_name.to_string(), alias);
+    }
+    pub fn port(&self, pw_name: &str, port_name: &str) -> Option<&str> {
+        self.ports
+            .get(&port_key(pw_name, port_name))
+            .map(String::as_str)
+    }
+    pub fn set_port(&mut self, pw_name: &str, port_name: &str, alias: Option<String>) {
+        set(&mut self.ports, port_key(pw_name, port_name), alias);
+    }
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty() && self.ports.is_empty()
+    }
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.ports.clear();
+    }
+}
@@ -0,0 +1,129 @@
+use super::Id;
+
+/// Spacing of the grid nodes snap to, in grid space
+pub const GRID_SIZE: f32 = 20.0;
+
+/// Ways of lining up the selected nodes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arrangement {
+    AlignLeft,
+    AlignRight,
+    AlignTop,
+    AlignBottom,
+    /// Equal gaps between nodes, from the leftmost to the rightmost one
+    DistributeHorizontally,
+    /// Equal gaps between nodes, from the topmost to the bottommost one
+    DistributeVertically,
+}
+
+impl Arrangement {
+    pub const ALL: [Arrangement; 6] = [
+        Arrangement::AlignLeft,
+        Arrangement::AlignRight,
+        Arrangement::AlignTop,
+        Arrangement::AlignBottom,
+        Arrangement::DistributeHorizontally,
+        Arrangement::DistributeVertically,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Arrangement::AlignLeft => "Align left",
+            Arrangement::AlignRight => "Align right",
+            Arrangement::AlignTop => "Align top",
+            Arrangement::AlignBottom => "Align bottom",
+            Arrangement::DistributeHorizontally => "Distribute horizontally",
+            Arrangement::DistributeVertically => "Distribute vertically",
+        }
+    }
+    /// Number of selected nodes needed for the arrangement to do anything
+    pub fn min_nodes(&self) -> usize {
+        match self {
+            Arrangement::DistributeHorizontally | Arrangement::DistributeVertically => 3,
+            _ => 2,
+        }
+    }
+    /// New positions of the nodes, given the rectangles they cover in grid space
+    pub fn apply(&self, rects: &[(Id, egui::Rect)]) -> Vec<(Id, egui::Pos2)> {
+        if rects.len() < self.min_nodes() {
+            return Vec::new();
+        }
+
+        let bounds = rects
+            .iter()
+            .fold(egui::Rect::NOTHING, |bounds, (_, rect)| bounds.union(*rect));
+
+        match self {
+            Arrangement::AlignLeft => rects
+                .iter()
+                .map(|(id, rect)| (*id, egui::pos2(bounds.left(), rect.top())))
+                .collect(),
+            Arrangement::AlignRight => rects
+                .iter()
+                .map(|(id, rect)| (*id, egui::pos2(bounds.right() - rect.width(), rect.top())))
+                .collect(),
+            Arrangement::AlignTop => rects
+                .iter()
+                .map(|(id, rect)| (*id, egui::pos2(rect.left(), bounds.top())))
+                .collect(),
+            Arrangement::AlignBottom => rects
+                .iter()
+                .map(|(id, rect)| {
+                    (
+                        *id,
+                        egui::pos2(rect.left(), bounds.bottom() - rect.height()),
+                    )
+                })
+                .collect(),
+            Arrangement::DistributeHorizontally => distribute(
+                rects,
+                bounds.width(),
+                |rect| rect.left(),
+                |rect| rect.width(),
+            )
+            .into_iter()
+            .map(|(id, rect, x)| (id, egui::pos2(bounds.left() + x, rect.top())))
+            .collect(),
+            Arrangement::DistributeVertically => distribute(
+                rects,
+                bounds.height(),
+                |rect| rect.top(),
+                |rect| rect.height(),
+            )
+            .into_iter()
+            .map(|(id, rect, y)| (id, egui::pos2(rect.left(), bounds.top() + y)))
+            .collect(),
+        }
+    }
+}
+
+/// Offsets of the nodes along one axis, from the start of the bounds, leaving equal gaps between them
+fn distribute(
+    rects: &[(Id, egui::Rect)],
+    extent: f32,
+    start: impl Fn(&egui::Rect) -> f32,
+    size: impl Fn(&egui::Rect) -> f32,
+) -> Vec<(Id, egui::Rect, f32)> {
+    let mut rects = rects.to_vec();
+    rects.sort_by(|(_, a), (_, b)| start(a).total_cmp(&start(b)));
+
+    let total = rects.iter().map(|(_, rect)| size(rect)).sum::<f32>();
+    let gap = (extent - total) / (rects.len() - 1) as f32;
+
+    let mut offset = 0.0;
+    rects
+        .into_iter()
+        .map(|(id, rect)| {
+            let position = offset;
+            offset += size(&rect) + gap;
+            (id, rect, position)
+        })
+        .collect()
+}
+
+pub fn snap(position: egui::Pos2) -> egui::Pos2 {
+    egui::pos2(
+        (position.x / GRID_SIZE).round() * GRID_SIZE,
+        (position.y / GRID_SIZE).round() * GRID_SIZE,
+    )
+}
@@ -0,0 +1,37 @@
+/// An application connected to pipewire
+#[derive(Debug)]
+pub struct Client {
+    id: u32,
+    name: String,
+    pid: Option<u32>,
+    binary: Option<String>,
+}
+
+impl Client {
+    pub fn new(id: u32, name: String, pid: Option<u32>, binary: Option<String>) -> Self {
+        Self {
+            id,
+            name,
+            pid,
+            binary,
+        }
+    }
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn describe(&self) -> String {
+        let mut description = format!("Client {}", self.id);
+
+        if let Some(pid) = self.pid {
+            description.push_str(&format!("\nPID: {}", pid));
+        }
+        if let Some(binary) = &self.binary {
+            description.push_str(&format!("\nBinary: {}", binary));
+        }
+
+        description
+    }
+}
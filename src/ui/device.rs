@@ -0,0 +1,157 @@
+use crate::pipewire_impl::{DeviceUpdate, PortType, Profile, Route};
+
+use super::node::Node;
+
+/// Changes to a device requested through the device panel
+pub enum DeviceAction {
+    SetProfile(i32),
+    SetRoute { device: i32, index: i32 },
+}
+
+#[derive(Debug)]
+pub struct Device {
+    id: u32,
+    name: String,
+    pw_name: String,
+    profiles: Vec<Profile>,
+    active_profile: Option<i32>,
+    routes: Vec<Route>,
+    active_routes: Vec<Route>,
+}
+
+impl Device {
+    pub fn new(id: u32, name: String, pw_name: String) -> Self {
+        Self {
+            id,
+            name,
+            pw_name,
+            profiles: Vec::new(),
+            active_profile: None,
+            routes: Vec::new(),
+            active_routes: Vec::new(),
+        }
+    }
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn update(&mut self, update: DeviceUpdate) {
+        match update {
+            DeviceUpdate::Profiles { index, profile } => {
+                if index == 0 {
+                    self.profiles.clear();
+                }
+                self.profiles.push(profile);
+            }
+            DeviceUpdate::ActiveProfile(index) => self.active_profile = index,
+            DeviceUpdate::Routes { index, route } => {
+                if index == 0 {
+                    self.routes.clear();
+                }
+                self.routes.push(route);
+            }
+            DeviceUpdate::ActiveRoutes { index, route } => {
+                if index == 0 {
+                    self.active_routes.clear();
+                }
+                self.active_routes.push(route);
+            }
+        }
+    }
+    fn profile_name(&self, index: i32) -> Option<&str> {
+        self.profiles
+            .iter()
+            .find(|profile| profile.index == index)
+            .map(|profile| profile.description.as_str())
+    }
+    /// Shows the profiles and routes of the device along with its nodes, returns the change picked by the user
+    pub fn draw(&self, ui: &mut egui::Ui, nodes: &[&Node]) -> Option<DeviceAction> {
+        let mut action = None;
+
+        egui::CollapsingHeader::new(format!("{} [{}]", self.name, self.id))
+            .id_source(("device", self.id))
+            .default_open(true)
+            .show(ui, |ui| {
+                ui.label(&self.pw_name);
+
+                let active_profile = self
+                    .active_profile
+                    .and_then(|index| self.profile_name(index))
+                    .unwrap_or("None");
+
+                egui::ComboBox::from_id_source(("device_profile", self.id))
+                    .selected_text(active_profile)
+                    .width(ui.available_width())
+                    .show_ui(ui, |ui| {
+                        for profile in self.profiles.iter() {
+                            let selected = self.active_profile == Some(profile.index);
+
+                            let response = ui
+                                .add_enabled(
+                                    profile.available,
+                                    egui::SelectableLabel::new(selected, &profile.description),
+                                )
+                                .on_hover_text(&profile.name);
+
+                            if response.clicked() && !selected {
+                                action = Some(DeviceAction::SetProfile(profile.index));
+                            }
+                        }
+                    });
+
+                // Every card device of the profile has its own active route
+                for active in self.active_routes.iter() {
+                    let direction = match active.direction {
+                        PortType::Input => "Input",
+                        PortType::Output => "Output",
+                        PortType::Unknown => "Route",
+                    };
+
+                    ui.horizontal(|ui| {
+                        ui.label(direction);
+
+                        egui::ComboBox::from_id_source(("device_route", self.id, active.device))
+                            .selected_text(&active.description)
+                            .show_ui(ui, |ui| {
+                                let routes = self.routes.iter().filter(|route| {
+                                    route.direction == active.direction
+                                        && self.active_profile.map_or(true, |profile| {
+                                            route.profiles.is_empty()
+                                                || route.profiles.contains(&profile)
+                                        })
+                                });
+
+                                for route in routes {
+                                    let selected = route.index == active.index;
+
+                                    let response = ui
+                                        .add_enabled(
+                                            route.available,
+                                            egui::SelectableLabel::new(
+                                                selected,
+                                                &route.description,
+                                            ),
+                                        )
+                                        .on_hover_text(&route.name);
+
+                                    if response.clicked() && !selected {
+                                        action = Some(DeviceAction::SetRoute {
+                                            device: active.device,
+                                            index: route.index,
+                                        });
+                                    }
+                                }
+                            });
+                    });
+                }
+
+                for node in nodes {
+                    ui.label(format!("• {}", node.display_name()));
+                }
+            });
+
+        action
+    }
+}
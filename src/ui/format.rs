@@ -0,0 +1,40 @@
+use crate::pipewire_impl::{Format, FormatUpdate};
+
+/// Format params of a node or a port as last reported by pipewire
+#[derive(Debug, Default)]
+pub struct Formats {
+    pub negotiated: Option<Format>,
+    pub supported: Vec<Format>,
+}
+
+impl Formats {
+    pub fn update(&mut self, update: FormatUpdate) {
+        match update {
+            FormatUpdate::Negotiated(format) => self.negotiated = format,
+            FormatUpdate::Supported { index, format } => {
+                if index == 0 {
+                    self.supported.clear();
+                }
+                if !self.supported.contains(&format) {
+                    self.supported.push(format);
+                }
+            }
+        }
+    }
+    /// Text shown when hovering over a port or a node
+    pub fn describe(&self) -> String {
+        let mut description = match &self.negotiated {
+            Some(format) => format!("Format: {}", format),
+            None => "Format: not negotiated".to_string(),
+        };
+
+        if !self.supported.is_empty() {
+            description.push_str("\nSupported:");
+            for format in &self.supported {
+                description.push_str(&format!("\n  {}", format));
+            }
+        }
+
+        description
+    }
+}
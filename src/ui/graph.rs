@@ -1,12 +1,25 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 
 use egui_nodes::{LinkArgs, NodeArgs, NodeConstructor};
 
-use crate::pipewire_impl::MediaType;
+use crate::pipewire_impl::{
+    DefaultKind, FormatUpdate, LinkOptions, LinkOrigin, LinkProperties, PortType, Scheduling,
+};
 
 use super::id::Id;
 
-use super::{link::Link, node::Node, port::Port, Theme};
+use super::{
+    alias::Aliases,
+    arrange::{self, Arrangement},
+    client::Client,
+    history::{LinkRef, PortRef},
+    link::{Link, LinkError},
+    link_menu::{LinkAction, LinkMenu},
+    node::{Node, PwNode},
+    node_menu::{Disconnect, NodeAction, NodeMenu},
+    port::{Port, PortFilter},
+    Theme,
+};
 
 /// Represents changes to any links that might have happend in the ui
 /// These changes are used to send updates to the pipewire thread
@@ -16,21 +29,98 @@ pub enum LinkUpdate {
         to_port: u32,
         from_node: u32,
         to_node: u32,
+        options: LinkOptions,
     },
     Removed(u32),
+    /// An end of a link was dragged to another port
+    Replaced {
+        old: u32,
+        from_port: u32,
+        to_port: u32,
+        options: LinkOptions,
+    },
+}
+
+/// Seconds during which the reason for refusing a link is shown
+const LINK_ERROR_DURATION: f64 = 3.0;
+
+/// Thickness of passive links, relative to the one of regular links
+const PASSIVE_THICKNESS: f32 = 0.5;
+
+/// node.name of the default sink and source, both as configured by the user and as currently used
+#[derive(Default)]
+pub struct Defaults {
+    configured_sink: Option<String>,
+    configured_source: Option<String>,
+    sink: Option<String>,
+    source: Option<String>,
+}
+
+impl Defaults {
+    pub fn set(&mut self, kind: DefaultKind, configured: bool, pw_name: Option<String>) {
+        let default = match (kind, configured) {
+            (DefaultKind::Sink, true) => &mut self.configured_sink,
+            (DefaultKind::Source, true) => &mut self.configured_source,
+            (DefaultKind::Sink, false) => &mut self.sink,
+            (DefaultKind::Source, false) => &mut self.source,
+        };
+
+        *default = pw_name;
+    }
+    /// The configured default, or the one in use if the user never picked one
+    pub fn get(&self, kind: DefaultKind) -> Option<&str> {
+        match kind {
+            DefaultKind::Sink => self.configured_sink.as_ref().or(self.sink.as_ref()),
+            DefaultKind::Source => self.configured_source.as_ref().or(self.source.as_ref()),
+        }
+        .map(|name| name.as_str())
+    }
 }
 
 pub struct Graph {
     nodes_ctx: egui_nodes::Context,
     nodes: HashMap<Id, Node>,  //Node id to Node
     links: HashMap<u32, Link>, //Link id to Link
+    node_menu: NodeMenu,
+    link_menu: LinkMenu,
+    /// Options of the links created by dragging between ports
+    pub link_options: LinkOptions,
+    /// Create links with mismatched media types or looping on a node anyway
+    pub allow_invalid_links: bool,
+    /// Why the last link was refused, and when
+    link_error: Option<(LinkError, f64)>,
+    selected_links: HashSet<u32>,
+    /// Link whose end is being dragged, it is only removed once the drag ends
+    detached_link: Option<u32>,
+    /// Positions of the nodes when the primary button was pressed
+    drag_start: Option<HashMap<Id, egui::Pos2>>,
+    /// Nodes moved by the user, along with their previous and new position
+    node_moves: Vec<(Id, egui::Pos2, egui::Pos2)>,
+    /// Selected nodes, ctrl clicks are only applied here since egui_nodes can't add nodes to its own selection
+    selection: Vec<Id>,
+    /// Round the position of moved nodes to the grid
+    pub snap_to_grid: bool,
+    /// Shows pipewire ids of nodes and ports
+    debug_view: bool,
+    /// Node opened in the inspector from its menu, shown instead of the selected one
+    inspected: Option<Id>,
+    pub aliases: Aliases,
+    pub port_filter: PortFilter,
+    pub defaults: Defaults,
+    pub stream_targets: HashMap<u32, String>, //Stream node id to target.object
+    pub driver_clocks: HashMap<u32, (i64, u32)>, //Driver id to the quantum and rate reported by the profiler
+    clients: HashMap<u32, Client>,               //Client id to Client
+    pub show_client_groups: bool,
+    pub show_driver_groups: bool,
+    pub highlighted_client: Option<u32>,
 }
 
 impl Graph {
     pub fn new() -> Self {
         //context.attribute_flag_push(egui_nodes::AttributeFlags::EnableLinkCreationOnSnap);
-        //context.attribute_flag_push(egui_nodes::AttributeFlags::EnableLinkDetachWithDragClick);
         let mut nodes_ctx = egui_nodes::Context::default();
+        // Links are re-routed by dragging one of their ends to another port
+        nodes_ctx.attribute_flag_push(egui_nodes::AttributeFlags::EnableLinkDetachWithDragClick);
 
         nodes_ctx.style.link_bezier_offset_coefficient = egui::vec2(0.50, 0.0);
         nodes_ctx.style.link_line_segments_per_length = 0.15;
@@ -39,6 +129,28 @@ impl Graph {
             nodes_ctx,
             nodes: HashMap::new(),
             links: HashMap::new(),
+            node_menu: NodeMenu::default(),
+            link_menu: LinkMenu::default(),
+            link_options: LinkOptions::default(),
+            allow_invalid_links: false,
+            link_error: None,
+            selected_links: HashSet::new(),
+            detached_link: None,
+            drag_start: None,
+            node_moves: Vec::new(),
+            selection: Vec::new(),
+            snap_to_grid: false,
+            debug_view: false,
+            inspected: None,
+            aliases: Aliases::default(),
+            port_filter: PortFilter::default(),
+            defaults: Defaults::default(),
+            stream_targets: HashMap::new(),
+            driver_clocks: HashMap::new(),
+            clients: HashMap::new(),
+            show_client_groups: false,
+            show_driver_groups: false,
+            highlighted_client: None,
         }
     }
     fn get_or_create_node(&mut self, name: String) -> &mut Node {
@@ -49,21 +161,30 @@ impl Graph {
             Node::new(id, name)
         })
     }
-    pub fn add_node(
-        &mut self,
-        name: String,
-        id: u32,
-        description: Option<String>,
-        media_type: Option<MediaType>,
-    ) {
-        self.get_or_create_node(name)
-            .add_pw_node(id, description, media_type)
+    pub fn add_node(&mut self, name: String, pw_node: PwNode) {
+        let node = self.get_or_create_node(name);
+        node.add_pw_node(pw_node);
+
+        let node_id = node.id();
+        self.apply_aliases(node_id);
+    }
+    fn apply_aliases(&mut self, node_id: Id) {
+        if let Some(node) = self.nodes.get_mut(&node_id) {
+            node.apply_aliases(&self.aliases);
+        }
     }
     pub fn remove_node(&mut self, name: &str, id: u32) {
         let mut remove_ui_node = false;
 
+        self.stream_targets.remove(&id);
+        self.driver_clocks.remove(&id);
+
         if let Some(node) = self.nodes.get_mut(&Id::new(name)) {
             remove_ui_node = node.remove_pw_node(id);
+
+            if remove_ui_node {
+                self.selection.retain(|&node_id| node_id != node.id());
+            }
         } else {
             log::error!("Node with name: {} was not registered", name);
         }
@@ -78,8 +199,40 @@ impl Graph {
             log::debug!("Removing node {}", removed_node.name());
         }
     }
+    pub fn add_client(&mut self, client: Client) {
+        self.clients.insert(client.id(), client);
+    }
+    pub fn remove_client(&mut self, id: u32) {
+        self.clients.remove(&id);
+
+        if self.highlighted_client == Some(id) {
+            self.highlighted_client = None;
+        }
+    }
+    /// Clients sorted by name
+    pub fn clients(&self) -> Vec<&Client> {
+        let mut clients = self.clients.values().collect::<Vec<_>>();
+        clients.sort_by(|a, b| natord::compare(a.name(), b.name()));
+
+        clients
+    }
+    /// Nodes created by a client, sorted by name
+    pub fn nodes_of_client(&self, client_id: u32) -> Vec<&Node> {
+        let mut nodes = self
+            .nodes
+            .values()
+            .filter(|node| node.belongs_to_client(client_id))
+            .collect::<Vec<_>>();
+        nodes.sort_by(|a, b| natord::compare(a.name(), b.name()));
+
+        nodes
+    }
     pub fn add_port(&mut self, node_name: String, node_id: u32, port: Port) {
-        self.get_or_create_node(node_name).add_port(node_id, port)
+        let node = self.get_or_create_node(node_name);
+        node.add_port(node_id, port);
+
+        let node_id = node.id();
+        self.apply_aliases(node_id);
     }
     pub fn remove_port(&mut self, node_name: &str, node_id: u32, port_id: u32) {
         if let Some(node) = self.nodes.get_mut(&Id::new(node_name)) {
@@ -88,6 +241,426 @@ impl Graph {
             log::error!("Node with name: {} was not registered", node_name);
         }
     }
+    pub fn update_node_format(&mut self, name: &str, id: u32, update: FormatUpdate) {
+        if let Some(node) = self.nodes.get_mut(&Id::new(name)) {
+            node.update_format(id, update);
+        } else {
+            log::warn!("Node with name: {} was not registered", name);
+        }
+    }
+    pub fn update_node_scheduling(&mut self, name: &str, id: u32, scheduling: Scheduling) {
+        if let Some(node) = self.nodes.get_mut(&Id::new(name)) {
+            node.update_scheduling(id, scheduling);
+        } else {
+            log::warn!("Node with name: {} was not registered", name);
+        }
+    }
+    pub fn update_port_format(
+        &mut self,
+        node_name: &str,
+        node_id: u32,
+        port_id: u32,
+        update: FormatUpdate,
+    ) {
+        if let Some(node) = self.nodes.get_mut(&Id::new(node_name)) {
+            node.update_port_format(node_id, port_id, update);
+        } else {
+            log::warn!("Node with name: {} was not registered", node_name);
+        }
+    }
+    /// Shows the context menu of a node if one was opened, returns the action picked by the user
+    pub fn node_menu(&mut self, ctx: &egui::Context) -> Option<NodeAction> {
+        self.node_menu.show(ctx, &self.nodes, &self.stream_targets)
+    }
+    pub fn link_menu(&mut self, ctx: &egui::Context) -> Option<LinkAction> {
+        self.link_menu.show(ctx, &self.links)
+    }
+    /// Screen space rectangle covered by a node, only available once it has been drawn
+    fn node_rect(&self, node_id: Id) -> Option<egui::Rect> {
+        let ui_id = node_id.value() as usize;
+
+        let position = self.nodes_ctx.get_node_pos_screen_space(ui_id)?;
+        let size = self.nodes_ctx.get_node_dimensions(ui_id)?;
+
+        Some(egui::Rect::from_min_size(position, size))
+    }
+    /// Outlines a group of nodes with a frame, labeled at the top left
+    fn draw_group<'a>(
+        &self,
+        ui: &egui::Ui,
+        nodes: impl Iterator<Item = &'a Node>,
+        stroke: egui::Stroke,
+        label: Option<&str>,
+    ) {
+        let rect = nodes
+            .filter(|node| !node.hidden)
+            .filter_map(|node| self.node_rect(node.id()))
+            .reduce(|group, rect| group.union(rect));
+
+        if let Some(rect) = rect {
+            let rect = rect.expand(8.0);
+            let painter = ui.painter();
+
+            painter.rect_stroke(rect, 4.0, stroke);
+            if let Some(label) = label {
+                painter.text(
+                    rect.left_top() - egui::vec2(0.0, 2.0),
+                    egui::Align2::LEFT_BOTTOM,
+                    label,
+                    egui::FontId::proportional(12.0),
+                    stroke.color,
+                );
+            }
+        }
+    }
+    /// Finds a node using the id it was given in the egui_nodes context
+    fn get_node_by_ui_id(&self, ui_id: usize) -> Option<&Node> {
+        self.nodes
+            .values()
+            .find(|node| node.id().value() as usize == ui_id)
+    }
+    pub fn selected_node(&self) -> Option<&Node> {
+        self.selection
+            .first()
+            .and_then(|node_id| self.nodes.get(node_id))
+    }
+    /// The node picked with "Inspect properties", or the selected one
+    pub fn inspected_node(&self) -> Option<&Node> {
+        self.inspected
+            .and_then(|node_id| self.nodes.get(&node_id))
+            .or_else(|| self.selected_node())
+    }
+    pub fn inspect(&mut self, node_id: Id) {
+        self.inspected = Some(node_id);
+    }
+    /// Sets the alias of every pipewire node making up the node
+    pub fn rename_node(&mut self, node_id: Id, alias: Option<String>) {
+        if let Some(node) = self.nodes.get(&node_id) {
+            for pw_name in node.pw_names() {
+                self.aliases.set_node(pw_name, alias.clone());
+            }
+        }
+        self.apply_aliases(node_id);
+    }
+    pub fn rename_ports(&mut self, node_id: Id, aliases: Vec<(u32, Option<String>)>) {
+        if let Some(node) = self.nodes.get(&node_id) {
+            for (port_id, alias) in aliases {
+                if let Some((pw_name, port_name)) = node.port_key(port_id) {
+                    self.aliases.set_port(pw_name, port_name, alias);
+                }
+            }
+        }
+        self.apply_aliases(node_id);
+    }
+    /// Forgets every alias, including the ones of nodes which aren't there anymore
+    pub fn clear_aliases(&mut self) {
+        self.aliases.clear();
+
+        for node in self.nodes.values_mut() {
+            node.apply_aliases(&self.aliases);
+        }
+    }
+    pub fn set_collapsed(&mut self, nodes: &[Id], collapsed: bool) {
+        for node_id in nodes {
+            if let Some(node) = self.nodes.get_mut(node_id) {
+                node.collapsed = collapsed;
+            }
+        }
+    }
+    pub fn hide_nodes(&mut self, nodes: &[Id]) {
+        for node_id in nodes {
+            if let Some(node) = self.nodes.get_mut(node_id) {
+                node.hidden = true;
+            }
+            self.selection.retain(|id| id != node_id);
+        }
+    }
+    pub fn hidden_nodes(&self) -> usize {
+        self.nodes.values().filter(|node| node.hidden).count()
+    }
+    pub fn show_hidden_nodes(&mut self) {
+        for node in self.nodes.values_mut() {
+            node.hidden = false;
+        }
+    }
+    /// Whether the ports of the node are drawn, links can only be attached to drawn ports
+    fn ports_shown(&self, node_id: Id) -> bool {
+        self.nodes
+            .get(&node_id)
+            .map_or(false, |node| !node.hidden && !node.collapsed)
+    }
+    /// Nodes selected in egui_nodes, by clicking or by dragging a box around them
+    fn ui_selection(&self) -> Vec<Id> {
+        self.nodes_ctx
+            .get_selected_nodes()
+            .iter()
+            .filter_map(|&ui_id| self.get_node_by_ui_id(ui_id))
+            .filter(|node| !node.hidden)
+            .map(|node| node.id())
+            .collect()
+    }
+    pub fn selected_nodes(&self) -> Vec<Id> {
+        self.selection.clone()
+    }
+    /// The visible node which was under the pointer when the primary button was pressed
+    fn pressed_node(&self, origin: egui::Pos2) -> Option<Id> {
+        self.nodes
+            .values()
+            .filter(|node| !node.hidden)
+            .map(|node| node.id())
+            .find(|&node_id| {
+                // The node may have been dragged since
+                let moved = self
+                    .drag_start
+                    .as_ref()
+                    .and_then(|start| {
+                        let from = start.get(&node_id)?;
+                        let to = self
+                            .nodes_ctx
+                            .get_node_pos_grid_space(node_id.value() as usize)?;
+                        Some(to - *from)
+                    })
+                    .unwrap_or(egui::Vec2::ZERO);
+
+                self.node_rect(node_id)
+                    .map_or(false, |rect| rect.translate(-moved).contains(origin))
+            })
+    }
+    /// Follows the clicks and box selections of egui_nodes and applies ctrl clicks on top of them
+    fn update_selection(&mut self, ui: &egui::Ui) {
+        let (pressed, down, ctrl, origin) = {
+            let input = ui.input();
+            (
+                input.pointer.primary_pressed(),
+                input.pointer.primary_down(),
+                input.modifiers.ctrl,
+                input.pointer.press_origin(),
+            )
+        };
+
+        // Presses on menus and windows above the graph are left alone
+        let origin = origin.filter(|&origin| ui.ctx().layer_id_at(origin) == Some(ui.layer_id()));
+
+        if !down || origin.is_none() {
+            return;
+        }
+
+        let pressed_node = origin.and_then(|origin| self.pressed_node(origin));
+
+        // Clicking a node brings the inspector back to the selection
+        if pressed && pressed_node.is_some() {
+            self.inspected = None;
+        }
+
+        let ui_selection = self.ui_selection();
+
+        match pressed_node {
+            Some(node_id) if ctrl => {
+                if pressed {
+                    if let Some(index) = self.selection.iter().position(|&id| id == node_id) {
+                        self.selection.remove(index);
+                    } else {
+                        self.selection.push(node_id);
+                    }
+                }
+            }
+            Some(node_id) if self.selection.contains(&node_id) => {
+                self.drag_selection(node_id, &ui_selection)
+            }
+            // Ctrl clicks on the canvas keep the selection
+            None if ctrl => {}
+            _ => self.selection = ui_selection,
+        }
+    }
+    /// Moves the selected nodes which egui_nodes doesn't move itself along with the dragged one
+    fn drag_selection(&mut self, dragged: Id, ui_selection: &[Id]) {
+        let start = match &self.drag_start {
+            Some(start) => start,
+            None => return,
+        };
+        let offset = match (
+            start.get(&dragged),
+            self.nodes_ctx
+                .get_node_pos_grid_space(dragged.value() as usize),
+        ) {
+            (Some(&from), Some(to)) => to - from,
+            _ => return,
+        };
+
+        let followers = self
+            .selection
+            .iter()
+            .filter(|&node_id| *node_id != dragged && !ui_selection.contains(node_id))
+            .filter_map(|node_id| Some((*node_id, *start.get(node_id)? + offset)))
+            .collect::<Vec<_>>();
+
+        for (node_id, position) in followers {
+            self.set_node_position(node_id, position);
+        }
+    }
+    /// Ids of the links going into, out of, or both, any of the nodes
+    pub fn links_of(&self, nodes: &[Id], which: Disconnect) -> Vec<u32> {
+        self.links
+            .values()
+            .filter(|link| {
+                let input = nodes.contains(&link.to_node);
+                let output = nodes.contains(&link.from_node);
+
+                match which {
+                    Disconnect::Inputs => input,
+                    Disconnect::Outputs => output,
+                    Disconnect::All => input || output,
+                }
+            })
+            .map(|link| link.id)
+            .collect()
+    }
+    /// Nodes belonging to a device, sorted by name
+    pub fn nodes_of_device(&self, device_id: u32) -> Vec<&Node> {
+        let mut nodes = self
+            .nodes
+            .values()
+            .filter(|node| node.belongs_to_device(device_id))
+            .collect::<Vec<_>>();
+        nodes.sort_by(|a, b| natord::compare(a.name(), b.name()));
+
+        nodes
+    }
+    pub fn nodes_with_unknown_ports(&self) -> impl Iterator<Item = &Node> {
+        self.nodes.values().filter(|node| node.has_unknown_ports())
+    }
+    fn get_port(&self, node_id: Id, port_id: u32) -> Option<&Port> {
+        self.nodes
+            .get(&node_id)
+            .and_then(|node| node.get_port(port_id))
+    }
+    /// Pairs of output and input ports linking every audio channel of `from` to `to`, leaving out existing links
+    pub fn channel_pairs(&self, from: Id, to: Id) -> Vec<(u32, u32)> {
+        let (outputs, inputs) = match (self.nodes.get(&from), self.nodes.get(&to)) {
+            (Some(from), Some(to)) => (
+                from.audio_ports(PortType::Output),
+                to.audio_ports(PortType::Input),
+            ),
+            _ => return Vec::new(),
+        };
+
+        let mut pairs = Vec::new();
+
+        if outputs.len() == 1 {
+            // Mono to anything: the channel goes everywhere
+            pairs.extend(inputs.iter().map(|input| (outputs[0].id(), input.id())));
+        } else if inputs.len() == 1 {
+            // Anything to mono: every channel is mixed down
+            pairs.extend(outputs.iter().map(|output| (output.id(), inputs[0].id())));
+        } else {
+            for output in outputs.iter() {
+                pairs.extend(
+                    inputs
+                        .iter()
+                        .filter(|input| {
+                            input.channel().is_some() && input.channel() == output.channel()
+                        })
+                        .map(|input| (output.id(), input.id())),
+                );
+            }
+
+            // Ports without matching positions are linked in order
+            if pairs.is_empty() {
+                pairs.extend(
+                    outputs
+                        .iter()
+                        .zip(inputs.iter())
+                        .map(|(output, input)| (output.id(), input.id())),
+                );
+            }
+        }
+
+        pairs.retain(|&(from_port, to_port)| {
+            !self
+                .links
+                .values()
+                .any(|link| link.from_port == from_port && link.to_port == to_port)
+        });
+
+        pairs
+    }
+    /// Checks a link drawn between two (ui node id, port id) pairs, returns its output and input ends
+    fn validate_link(
+        &self,
+        start: (usize, u32),
+        end: (usize, u32),
+        options: LinkOptions,
+    ) -> Result<((usize, u32), (usize, u32)), LinkError> {
+        let ports = self
+            .get_node_by_ui_id(start.0)
+            .and_then(|node| node.get_port(start.1))
+            .zip(
+                self.get_node_by_ui_id(end.0)
+                    .and_then(|node| node.get_port(end.1)),
+            );
+
+        let (start_port, end_port) = ports.ok_or(LinkError::PortGone)?;
+
+        // Links can be dragged from either end
+        let (from, to, (output, input)) = match (start_port.port_type(), end_port.port_type()) {
+            (PortType::Output, PortType::Input) => (start_port, end_port, (start, end)),
+            (PortType::Input, PortType::Output) => (end_port, start_port, (end, start)),
+            (PortType::Unknown, _) | (_, PortType::Unknown) => {
+                return Err(LinkError::UnknownDirection)
+            }
+            (port_type, _) => return Err(LinkError::SameDirection(port_type)),
+        };
+
+        // Ui nodes can be made of several pipewire nodes, only links within one of them loop
+        let pw_node_of = |(ui_id, port_id): (usize, u32)| {
+            self.get_node_by_ui_id(ui_id)
+                .and_then(|node| node.pw_node_of(port_id))
+        };
+        let self_loop = pw_node_of(output).is_some() && pw_node_of(output) == pw_node_of(input);
+
+        // Ports of unknown media type are assumed to be compatible with anything
+        let errors = [
+            match (from.media_type(), to.media_type()) {
+                (Some(a), Some(b)) if a != b => Some(LinkError::MediaTypeMismatch(a, b)),
+                _ => None,
+            },
+            self.links
+                .values()
+                .any(|link| link.from_port == from.id() && link.to_port == to.id())
+                .then_some(LinkError::Duplicate),
+            (self_loop && !options.feedback).then_some(LinkError::SelfLoop),
+        ];
+
+        let error = errors
+            .into_iter()
+            .flatten()
+            .find(|error| !(self.allow_invalid_links && error.can_override()));
+
+        match error {
+            Some(error) => Err(error),
+            None => Ok((output, input)),
+        }
+    }
+    /// Returns a description of the mismatch if the negotiated formats of both ends of the link differ
+    fn format_mismatch(&self, link: &Link) -> Option<String> {
+        let from = self.get_port(link.from_node, link.from_port)?;
+        let to = self.get_port(link.to_node, link.to_port)?;
+
+        let from_format = from.formats.negotiated.as_ref()?;
+        let to_format = to.formats.negotiated.as_ref()?;
+
+        if from_format.is_compatible(to_format) {
+            None
+        } else {
+            Some(format!(
+                "Format mismatch\n{}: {}\n{}: {}",
+                from.display_name(),
+                from_format,
+                to.display_name(),
+                to_format
+            ))
+        }
+    }
     pub fn add_link(
         &mut self,
         id: u32,
@@ -95,6 +668,7 @@ impl Graph {
         to_node_name: String,
         from_port: u32,
         to_port: u32,
+        properties: LinkProperties,
     ) {
         log::debug!(
             "{}.{}->{}.{}",
@@ -126,10 +700,12 @@ impl Graph {
                 from_port,
                 to_port,
                 active: true,
+                properties,
             },
         );
     }
     pub fn remove_link(&mut self, id: u32) -> Option<Link> {
+        self.selected_links.remove(&id);
         let removed = self.links.remove(&id);
         match removed {
             Some(ref link) => log::debug!("{}-x-{}", link.from_port, link.to_port),
@@ -137,10 +713,156 @@ impl Graph {
         }
         removed
     }
-    #[allow(dead_code)]
     fn get_link(&self, id: u32) -> Option<&Link> {
         self.links.get(&id)
     }
+    fn port_ref(&self, port_id: u32) -> Option<PortRef> {
+        self.nodes.values().find_map(|node| {
+            let port = node.get_port(port_id)?;
+            let (pw_name, _) = node.port_key(port_id)?;
+
+            Some(PortRef {
+                id: port_id,
+                node: pw_name.to_string(),
+                port: port.name().to_string(),
+                port_type: port.port_type(),
+            })
+        })
+    }
+    /// Current id of the port, it is only looked up by name if the recorded one is gone
+    fn resolve_port(&self, port: &PortRef) -> Option<u32> {
+        let unchanged = self
+            .nodes
+            .values()
+            .any(|node| node.port_key(port.id) == Some((port.node.as_str(), port.port.as_str())));
+        if unchanged {
+            return Some(port.id);
+        }
+
+        self.nodes
+            .values()
+            .find_map(|node| node.find_port_by_name(&port.node, &port.port, port.port_type))
+            .map(|port| port.id())
+    }
+    /// Identifies a link to be created between two ports
+    pub fn link_ref(&self, from_port: u32, to_port: u32, options: LinkOptions) -> Option<LinkRef> {
+        Some(LinkRef {
+            id: None,
+            from: self.port_ref(from_port)?,
+            to: self.port_ref(to_port)?,
+            options,
+            origin: options.origin(),
+        })
+    }
+    pub fn link_ref_of(&self, link_id: u32) -> Option<LinkRef> {
+        let link = self.get_link(link_id)?;
+
+        Some(LinkRef {
+            id: Some(link_id),
+            origin: link.properties.origin,
+            ..self.link_ref(link.from_port, link.to_port, link.properties.options())?
+        })
+    }
+    /// Current output and input port ids of a link, its nodes may have been recreated since
+    pub fn resolve_link(&self, link: &LinkRef) -> Option<(u32, u32)> {
+        Some((self.resolve_port(&link.from)?, self.resolve_port(&link.to)?))
+    }
+    /// Current id of an existing link, the recorded one is used while it still joins the same ports
+    pub fn resolve_link_id(&self, link: &LinkRef) -> Option<u32> {
+        let (from_port, to_port) = self.resolve_link(link)?;
+
+        match link.id.and_then(|id| self.get_link(id)) {
+            Some(existing) if existing.from_port == from_port && existing.to_port == to_port => {
+                Some(existing.id)
+            }
+            _ => self.link_between(from_port, to_port),
+        }
+    }
+    fn link_between(&self, from_port: u32, to_port: u32) -> Option<u32> {
+        self.links
+            .values()
+            .find(|link| link.from_port == from_port && link.to_port == to_port)
+            .map(|link| link.id)
+    }
+    /// Id of the pipewire node with the given node.name
+    pub fn find_pw_node(&self, pw_name: &str) -> Option<u32> {
+        self.nodes
+            .values()
+            .find_map(|node| node.find_pw_node(pw_name))
+    }
+    pub fn set_node_position(&mut self, node_id: Id, position: egui::Pos2) {
+        if let Some(node) = self.nodes.get_mut(&node_id) {
+            node.position = Some(position);
+            self.nodes_ctx
+                .set_node_pos_grid_space(node_id.value() as usize, position);
+        }
+    }
+    /// Lines up the selected nodes, the moves are recorded like the ones made by dragging
+    pub fn arrange_selection(&mut self, arrangement: Arrangement) {
+        let rects = self
+            .selected_nodes()
+            .into_iter()
+            .filter_map(|node_id| {
+                let ui_id = node_id.value() as usize;
+                let position = self.nodes_ctx.get_node_pos_grid_space(ui_id)?;
+                let size = self.nodes_ctx.get_node_dimensions(ui_id)?;
+
+                Some((node_id, egui::Rect::from_min_size(position, size)))
+            })
+            .collect::<Vec<_>>();
+
+        for (node_id, position) in arrangement.apply(&rects) {
+            let from = match rects.iter().find(|(id, _)| *id == node_id) {
+                Some((_, rect)) => rect.min,
+                None => continue,
+            };
+            let to = if self.snap_to_grid {
+                arrange::snap(position)
+            } else {
+                position
+            };
+
+            if from != to {
+                self.set_node_position(node_id, to);
+                self.node_moves.push((node_id, from, to));
+            }
+        }
+    }
+    /// Nodes moved by the user since the last call
+    pub fn take_node_moves(&mut self) -> Vec<(Id, egui::Pos2, egui::Pos2)> {
+        std::mem::take(&mut self.node_moves)
+    }
+    fn node_positions(&self) -> HashMap<Id, egui::Pos2> {
+        self.nodes
+            .keys()
+            .filter_map(|&id| {
+                let position = self
+                    .nodes_ctx
+                    .get_node_pos_grid_space(id.value() as usize)?;
+                Some((id, position))
+            })
+            .collect()
+    }
+    /// Node and port names of both ends of a link
+    fn describe_link(&self, link: &Link) -> String {
+        let end = |node_id: Id, port_id: u32| {
+            let node = self
+                .nodes
+                .get(&node_id)
+                .map_or("?", |node| node.display_name());
+            let port = self
+                .get_port(node_id, port_id)
+                .map_or("?", |port| port.display_name());
+
+            format!("{}:{}", node, port)
+        };
+
+        format!(
+            "{} → {}",
+            end(link.from_node, link.from_port),
+            end(link.to_node, link.to_port)
+        )
+    }
     #[allow(dead_code)]
     fn get_link_mut(&mut self, id: u32) -> Option<&mut Link> {
         self.links.get_mut(&id)
@@ -197,9 +919,12 @@ impl Graph {
         ctx: &'ui egui::Context,
         ui: &'ui mut egui::Ui,
         theme: &'ui Theme,
-    ) -> Option<LinkUpdate> {
-        // Ctrl is used to trigger the debug view
-        let debug_view = ctx.input().modifiers.ctrl;
+    ) -> Vec<LinkUpdate> {
+        // F12 toggles the debug view, Ctrl is taken by selection and undo
+        if ctx.input().key_pressed(egui::Key::F12) && !ctx.wants_keyboard_input() {
+            self.debug_view = !self.debug_view;
+        }
+        let debug_view = self.debug_view;
         let mut ui_nodes = Vec::with_capacity(self.nodes.len());
 
         self.nodes_ctx.style.colors[egui_nodes::ColorStyle::NodeBackground as usize] =
@@ -208,6 +933,10 @@ impl Graph {
             theme.node_background_hovered;
         self.nodes_ctx.style.colors[egui_nodes::ColorStyle::NodeBackgroundSelected as usize] =
             theme.node_background_hovered;
+        self.nodes_ctx.style.colors[egui_nodes::ColorStyle::BoxSelector as usize] =
+            theme.highlight.linear_multiply(0.2);
+        self.nodes_ctx.style.colors[egui_nodes::ColorStyle::BoxSelectorOutline as usize] =
+            theme.highlight;
 
         ui.vertical_centered(|ui| {
             if ui.button("Arrange").clicked() {
@@ -220,7 +949,7 @@ impl Graph {
             }
         });
 
-        for node in self.nodes.values() {
+        for node in self.nodes.values().filter(|node| !node.hidden) {
             let mut ui_node = NodeConstructor::new(
                 node.id().value() as usize,
                 NodeArgs {
@@ -231,21 +960,308 @@ impl Graph {
                 },
             );
 
-            node.draw(&mut ui_node, theme, debug_view);
+            node.draw(
+                &mut ui_node,
+                theme,
+                &self.port_filter,
+                &self.defaults,
+                debug_view,
+            );
 
             ui_nodes.push(ui_node);
         }
 
-        let links = self.links.values().map(|link| {
+        let mismatches = self
+            .links
+            .values()
+            .filter_map(|link| Some((link.id, self.format_mismatch(link)?)))
+            .collect::<HashMap<u32, String>>();
+
+        // Links can only be drawn if the ports on both of their ends are visible
+        let links = self.links.values().filter(|link| {
+            if self.detached_link == Some(link.id) {
+                return false;
+            }
+            if !self.ports_shown(link.from_node) || !self.ports_shown(link.to_node) {
+                return false;
+            }
+
+            let from = self.get_port(link.from_node, link.from_port);
+            let to = self.get_port(link.to_node, link.to_port);
+
+            from.map_or(false, |port| self.port_filter.is_visible(port))
+                && to.map_or(false, |port| self.port_filter.is_visible(port))
+        });
+
+        let links = links.map(|link| {
+            let mut args = if mismatches.contains_key(&link.id) {
+                LinkArgs {
+                    base: Some(theme.format_mismatch),
+                    hovered: Some(theme.format_mismatch),
+                    ..Default::default()
+                }
+            } else {
+                let media_type = self
+                    .get_port(link.from_node, link.from_port)
+                    .and_then(|port| port.media_type());
+
+                // Links created by pw-viz stand out from the ones of the session manager
+                match (link.properties.origin, media_type) {
+                    (LinkOrigin::Owned, _) => LinkArgs {
+                        base: Some(theme.owned_link),
+                        hovered: Some(theme.highlight),
+                        ..Default::default()
+                    },
+                    (LinkOrigin::Lingering, _) => LinkArgs {
+                        base: Some(theme.lingering_link),
+                        hovered: Some(theme.highlight),
+                        ..Default::default()
+                    },
+                    (LinkOrigin::External, Some(_)) => {
+                        let (base, hovered) = theme.port_colors(media_type);
+                        LinkArgs {
+                            base: Some(base),
+                            hovered: Some(hovered),
+                            ..Default::default()
+                        }
+                    }
+                    (LinkOrigin::External, None) => LinkArgs::default(),
+                }
+            };
+
+            // Passive links are drawn thinner, they don't keep their nodes running
+            if link.properties.passive {
+                args.thickness = Some(self.nodes_ctx.style.link_thickness * PASSIVE_THICKNESS);
+            }
+            if self.selected_links.contains(&link.id) {
+                args.base = Some(theme.highlight);
+            }
+
             (
                 link.id as usize,
                 link.from_port as usize,
                 link.to_port as usize,
-                LinkArgs::default(),
+                args,
             )
         });
+        let links = links.collect::<Vec<_>>();
+
+        self.nodes_ctx.show(ui_nodes, links.into_iter(), ui);
+
+        if self.show_client_groups {
+            for client in self.clients.values() {
+                let nodes = self
+                    .nodes
+                    .values()
+                    .filter(|node| node.belongs_to_client(client.id()));
+
+                self.draw_group(
+                    ui,
+                    nodes,
+                    egui::Stroke::new(1.0, theme.client_group),
+                    Some(client.name()),
+                );
+            }
+        }
+
+        if self.show_driver_groups {
+            let drivers = self
+                .nodes
+                .values()
+                .flat_map(|node| node.driver_ids())
+                .collect::<BTreeSet<_>>();
+
+            for driver_id in drivers {
+                let followers = self
+                    .nodes
+                    .values()
+                    .filter(|node| node.follows_driver(driver_id))
+                    .collect::<Vec<_>>();
+
+                // A driver without any followers isn't interesting
+                if followers.len() < 2 {
+                    continue;
+                }
+
+                // Only the profiler knows the clock the driver actually runs at
+                let label = self
+                    .driver_clocks
+                    .get(&driver_id)
+                    .filter(|&&(_, rate)| rate > 0)
+                    .map(|&(quantum, rate)| {
+                        let name = self
+                            .nodes
+                            .values()
+                            .find(|node| node.pw_ids().contains(&driver_id))
+                            .map_or_else(
+                                || format!("Driver {}", driver_id),
+                                |node| node.display_name().to_string(),
+                            );
+
+                        format!(
+                            "⏱ {} · {}/{} ({:.2} ms)",
+                            name,
+                            quantum,
+                            rate,
+                            quantum as f32 * 1000.0 / rate as f32
+                        )
+                    });
+
+                self.draw_group(
+                    ui,
+                    followers.into_iter(),
+                    egui::Stroke::new(2.0, driver_color(driver_id)),
+                    label.as_deref(),
+                );
+            }
+        }
+
+        self.update_selection(ui);
+
+        // egui_nodes only draws the nodes in its own selection as selected
+        let ui_selection = self.ui_selection();
+        for node_id in self
+            .selection
+            .iter()
+            .filter(|node_id| !ui_selection.contains(node_id))
+        {
+            if let Some(rect) = self.node_rect(*node_id) {
+                ui.painter().rect_stroke(
+                    rect.expand(2.0),
+                    4.0,
+                    egui::Stroke::new(2.0, theme.highlight),
+                );
+            }
+        }
+
+        if let Some(client_id) = self.highlighted_client {
+            for node in self.nodes_of_client(client_id) {
+                if let Some(rect) = self.node_rect(node.id()) {
+                    ui.painter().rect_stroke(
+                        rect.expand(2.0),
+                        4.0,
+                        egui::Stroke::new(2.0, theme.highlight),
+                    );
+                }
+            }
+        }
 
-        self.nodes_ctx.show(ui_nodes, links, ui);
+        if let Some((error, time)) = self.link_error {
+            if ctx.input().time - time < LINK_ERROR_DURATION {
+                egui::show_tooltip_at_pointer(ctx, egui::Id::new("link_error"), |ui| {
+                    ui.colored_label(theme.warning, error.describe());
+                    if error.can_override() {
+                        ui.label(
+                            "Enable \"Allow invalid links\" in the Links menu to create it anyway",
+                        );
+                    }
+                });
+            } else {
+                self.link_error = None;
+            }
+        }
+
+        if let Some(link) = self
+            .nodes_ctx
+            .get_hovered_link()
+            .and_then(|link_id| self.get_link(link_id as u32))
+        {
+            let description = self.describe_link(link);
+
+            egui::show_tooltip(ctx, egui::Id::new("link_tooltip"), |ui| {
+                ui.strong(format!("Link {}", link.id));
+                ui.label(description);
+                ui.separator();
+
+                if let Some(mismatch) = mismatches.get(&link.id) {
+                    ui.colored_label(theme.format_mismatch, mismatch);
+                    ui.separator();
+                }
+
+                let mut properties = link.properties.all.iter().collect::<Vec<_>>();
+                properties.sort();
+
+                egui::Grid::new("link_properties").show(ui, |ui| {
+                    for (key, value) in properties {
+                        ui.label(key);
+                        ui.label(value);
+                        ui.end_row();
+                    }
+                });
+            });
+        }
+
+        // Moves are only known once the nodes are released
+        if ctx.input().pointer.primary_pressed() {
+            self.drag_start = Some(self.node_positions());
+        }
+        if ctx.input().pointer.primary_released() {
+            if let Some(start) = self.drag_start.take() {
+                let end = self.node_positions();
+
+                for (node_id, from) in start {
+                    let to = match end.get(&node_id) {
+                        Some(&to) if to != from => to,
+                        _ => continue,
+                    };
+                    let to = if self.snap_to_grid {
+                        let snapped = arrange::snap(to);
+                        self.set_node_position(node_id, snapped);
+                        snapped
+                    } else {
+                        to
+                    };
+
+                    self.node_moves.push((node_id, from, to));
+                }
+            }
+        }
+
+        // Clicking a link selects it, shift adds it to the selection
+        if ctx.input().pointer.primary_clicked() {
+            let shift = ctx.input().modifiers.shift;
+
+            match self
+                .nodes_ctx
+                .get_hovered_link()
+                .map(|link_id| link_id as u32)
+            {
+                Some(link_id) if shift => {
+                    if !self.selected_links.remove(&link_id) {
+                        self.selected_links.insert(link_id);
+                    }
+                }
+                Some(link_id) => {
+                    self.selected_links.clear();
+                    self.selected_links.insert(link_id);
+                }
+                None if !shift => self.selected_links.clear(),
+                None => {}
+            }
+        }
+
+        if ctx.input().pointer.secondary_clicked() {
+            let hovered = self
+                .nodes_ctx
+                .get_hovered_node()
+                .and_then(|ui_id| self.get_node_by_ui_id(ui_id))
+                .map(|node| node.id());
+
+            let hovered_link = self
+                .nodes_ctx
+                .get_hovered_link()
+                .map(|link_id| link_id as u32);
+            let position = ctx.input().pointer.interact_pos();
+
+            match (hovered, hovered_link, position) {
+                (Some(node_id), _, Some(position)) => {
+                    let selection = self.selected_nodes();
+                    self.node_menu.open(node_id, position, selection)
+                }
+                (None, Some(link_id), Some(position)) => self.link_menu.open(link_id, position),
+                _ => {}
+            }
+        }
 
         let mut prev_pos = egui::pos2(ui.available_width() / 4.0, ui.available_height() / 2.0);
         let mut padding = egui::pos2(75.0, 150.0);
@@ -273,8 +1289,13 @@ impl Graph {
             }
         }
 
+        let mut updates = Vec::new();
+
         if let Some(link) = self.nodes_ctx.link_destroyed() {
-            Some(LinkUpdate::Removed(link as u32))
+            // Alt clicking a link also detaches it, letting go of it anywhere but a port removes it
+            if let Some(previous) = self.detached_link.replace(link as u32) {
+                updates.push(LinkUpdate::Removed(previous));
+            }
         } else if let Some((from_port, from_node, to_port, to_node, _)) =
             self.nodes_ctx.link_created_node()
         {
@@ -286,14 +1307,236 @@ impl Graph {
                 to_node
             );
 
-            Some(LinkUpdate::Created {
-                from_port: from_port as u32,
-                to_port: to_port as u32,
-                from_node: from_node as u32,
-                to_node: to_node as u32,
-            })
-        } else {
-            None
+            // Holding shift while dropping the link makes it passive
+            let mut options = self.link_options;
+            options.passive |= ctx.input().modifiers.shift;
+
+            match self.validate_link(
+                (from_node, from_port as u32),
+                (to_node, to_port as u32),
+                options,
+            ) {
+                Ok(((from_node, from_port), (to_node, to_port))) => {
+                    match self.detached_link.take().and_then(|id| self.get_link(id)) {
+                        // Dropped back where it was
+                        Some(old) if old.from_port == from_port && old.to_port == to_port => {}
+                        Some(old) => {
+                            // The re-routed link keeps its options
+                            let mut options = old.properties.options();
+                            options.passive |= ctx.input().modifiers.shift;
+
+                            updates.push(LinkUpdate::Replaced {
+                                old: old.id,
+                                from_port,
+                                to_port,
+                                options,
+                            })
+                        }
+                        None => updates.push(LinkUpdate::Created {
+                            from_port,
+                            to_port,
+                            from_node: from_node as u32,
+                            to_node: to_node as u32,
+                            options,
+                        }),
+                    }
+                }
+                Err(error) => {
+                    log::warn!(
+                        "Refusing to link port {} to {}: {:?}",
+                        from_port,
+                        to_port,
+                        error
+                    );
+                    self.link_error = Some((error, ctx.input().time));
+                    // A refused re-route leaves the old link in place
+                    self.detached_link = None;
+                }
+            }
         }
+
+        if !ctx.input().pointer.primary_down() {
+            if let Some(link) = self.detached_link.take() {
+                updates.push(LinkUpdate::Removed(link));
+            }
+        }
+
+        // Text fields use these keys too
+        let delete = !ctx.wants_keyboard_input()
+            && (ctx.input().key_pressed(egui::Key::Delete)
+                || ctx.input().key_pressed(egui::Key::Backspace));
+
+        if delete {
+            updates.extend(self.selected_links.drain().map(LinkUpdate::Removed));
+        }
+
+        updates
+    }
+}
+
+/// Distinct colors for the outlines of driver groups
+fn driver_color(driver_id: u32) -> egui::Color32 {
+    // Stepping by the golden ratio keeps the hues of consecutive ids far apart
+    let hue = (driver_id as f32 * 0.618_034).fract();
+
+    egui::color::Hsva::new(hue, 0.65, 0.9, 1.0).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipewire_impl::{MediaType, PortFlags};
+
+    fn ui_id(name: &str) -> usize {
+        Id::new(name).value() as usize
+    }
+
+    fn add_port(graph: &mut Graph, node: &str, node_id: u32, port: (u32, PortType, MediaType)) {
+        let (id, port_type, media_type) = port;
+
+        graph.add_port(
+            node.to_string(),
+            node_id,
+            Port::new(
+                id,
+                format!("port_{}", id),
+                port_type,
+                Some(media_type),
+                None,
+                PortFlags::default(),
+            ),
+        );
+    }
+
+    /// "a" has an audio output and input, "b" an audio and a midi input and a port of unknown direction
+    fn graph() -> Graph {
+        let mut graph = Graph::new();
+
+        for (name, id) in [("a", 1), ("b", 2)] {
+            graph.add_node(
+                name.to_string(),
+                PwNode::new(id, name.to_string(), None, None, None, None, None),
+            );
+        }
+        add_port(&mut graph, "a", 1, (10, PortType::Output, MediaType::Audio));
+        add_port(&mut graph, "a", 1, (11, PortType::Input, MediaType::Audio));
+        add_port(&mut graph, "b", 2, (20, PortType::Input, MediaType::Audio));
+        add_port(&mut graph, "b", 2, (21, PortType::Input, MediaType::Midi));
+        add_port(
+            &mut graph,
+            "b",
+            2,
+            (22, PortType::Unknown, MediaType::Audio),
+        );
+
+        graph
+    }
+
+    fn validate(
+        graph: &Graph,
+        start: (&str, u32),
+        end: (&str, u32),
+    ) -> Result<(u32, u32), LinkError> {
+        graph
+            .validate_link(
+                (ui_id(start.0), start.1),
+                (ui_id(end.0), end.1),
+                LinkOptions::default(),
+            )
+            .map(|((_, from_port), (_, to_port))| (from_port, to_port))
+    }
+
+    #[test]
+    fn links_are_ordered_from_output_to_input() {
+        let graph = graph();
+
+        assert_eq!(validate(&graph, ("a", 10), ("b", 20)), Ok((10, 20)));
+        assert_eq!(validate(&graph, ("b", 20), ("a", 10)), Ok((10, 20)));
+    }
+
+    #[test]
+    fn direction_errors() {
+        let graph = graph();
+
+        assert_eq!(
+            validate(&graph, ("a", 11), ("b", 20)),
+            Err(LinkError::SameDirection(PortType::Input))
+        );
+        assert_eq!(
+            validate(&graph, ("a", 10), ("b", 22)),
+            Err(LinkError::UnknownDirection)
+        );
+    }
+
+    #[test]
+    fn removed_ports_are_refused() {
+        let graph = graph();
+
+        assert_eq!(
+            validate(&graph, ("a", 10), ("b", 99)),
+            Err(LinkError::PortGone)
+        );
+        assert_eq!(
+            validate(&graph, ("c", 30), ("b", 20)),
+            Err(LinkError::PortGone)
+        );
+    }
+
+    #[test]
+    fn media_type_mismatch_can_be_overridden() {
+        let mut graph = graph();
+
+        assert_eq!(
+            validate(&graph, ("a", 10), ("b", 21)),
+            Err(LinkError::MediaTypeMismatch(
+                MediaType::Audio,
+                MediaType::Midi
+            ))
+        );
+
+        graph.allow_invalid_links = true;
+        assert_eq!(validate(&graph, ("a", 10), ("b", 21)), Ok((10, 21)));
+    }
+
+    #[test]
+    fn self_loops_need_a_feedback_link() {
+        let graph = graph();
+
+        assert_eq!(
+            validate(&graph, ("a", 10), ("a", 11)),
+            Err(LinkError::SelfLoop)
+        );
+
+        let options = LinkOptions {
+            feedback: true,
+            ..LinkOptions::default()
+        };
+        assert!(graph
+            .validate_link((ui_id("a"), 10), (ui_id("a"), 11), options)
+            .is_ok());
+    }
+
+    #[test]
+    fn duplicate_links_are_refused() {
+        let mut graph = graph();
+        graph.add_link(
+            100,
+            "a".to_string(),
+            "b".to_string(),
+            10,
+            20,
+            LinkProperties::from_props(None),
+        );
+
+        assert_eq!(
+            validate(&graph, ("a", 10), ("b", 20)),
+            Err(LinkError::Duplicate)
+        );
+        // Duplicates can't be overridden
+        graph.allow_invalid_links = true;
+        assert_eq!(
+            validate(&graph, ("b", 20), ("a", 10)),
+            Err(LinkError::Duplicate)
+        );
     }
 }
@@ -0,0 +1,190 @@
+use std::collections::VecDeque;
+
+use crate::pipewire_impl::{LinkOptions, LinkOrigin, PortType, VirtualDevice};
+
+use super::Id;
+
+/// Number of user actions that can be undone
+const HISTORY_LENGTH: usize = 100;
+
+/// A port, along with the names used to find it again if its node was recreated with new ids
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortRef {
+    /// Id of the port when the change was made
+    pub id: u32,
+    /// node.name of the pipewire node owning the port
+    pub node: String,
+    pub port: String,
+    pub port_type: PortType,
+}
+
+/// A link identified by the ports on both of its ends
+#[derive(Debug, Clone)]
+pub struct LinkRef {
+    /// Id of the link when the change was made, unknown for links which didn't exist yet
+    pub id: Option<u32>,
+    pub from: PortRef,
+    pub to: PortRef,
+    pub options: LinkOptions,
+    /// Links are recreated on behalf of whoever created them
+    pub origin: LinkOrigin,
+}
+
+/// A single change to the graph made by the user
+#[derive(Debug, Clone)]
+pub enum Change {
+    AddLink(LinkRef),
+    RemoveLink(LinkRef),
+    /// Moving an end of a link to another port
+    ReplaceLink {
+        old: LinkRef,
+        new: LinkRef,
+    },
+    /// Node ids along with their previous and new position
+    MoveNodes(Vec<(Id, egui::Pos2, egui::Pos2)>),
+    CreateVirtualDevice(VirtualDevice),
+    RemoveVirtualDevice(VirtualDevice),
+}
+
+impl Change {
+    fn inverse(&self) -> Change {
+        match self.clone() {
+            Change::AddLink(link) => Change::RemoveLink(link),
+            Change::RemoveLink(link) => Change::AddLink(link),
+            Change::ReplaceLink { old, new } => Change::ReplaceLink { old: new, new: old },
+            Change::MoveNodes(moves) => Change::MoveNodes(
+                moves
+                    .into_iter()
+                    .map(|(id, from, to)| (id, to, from))
+                    .collect(),
+            ),
+            Change::CreateVirtualDevice(device) => Change::RemoveVirtualDevice(device),
+            Change::RemoveVirtualDevice(device) => Change::CreateVirtualDevice(device),
+        }
+    }
+}
+
+/// Undo and redo stacks of user actions, an action being made of one or more changes
+#[derive(Default)]
+pub struct History {
+    undo: VecDeque<Vec<Change>>,
+    redo: Vec<Vec<Change>>,
+}
+
+impl History {
+    pub fn record(&mut self, changes: Vec<Change>) {
+        if changes.is_empty() {
+            return;
+        }
+
+        if self.undo.len() == HISTORY_LENGTH {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(changes);
+        self.redo.clear();
+    }
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+    /// Returns the changes reverting the last action, in the order they have to be applied
+    pub fn undo(&mut self) -> Vec<Change> {
+        let changes = match self.undo.pop_back() {
+            Some(changes) => changes,
+            None => return Vec::new(),
+        };
+
+        let inverse = changes.iter().rev().map(Change::inverse).collect();
+        self.redo.push(changes);
+
+        inverse
+    }
+    /// Returns the changes of the last undone action
+    pub fn redo(&mut self) -> Vec<Change> {
+        let changes = match self.redo.pop() {
+            Some(changes) => changes,
+            None => return Vec::new(),
+        };
+
+        self.undo.push_back(changes.clone());
+
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn move_to(x: f32) -> Vec<Change> {
+        vec![Change::MoveNodes(vec![(
+            Id::new("node"),
+            egui::pos2(0.0, 0.0),
+            egui::pos2(x, 0.0),
+        )])]
+    }
+
+    fn destination(changes: &[Change]) -> f32 {
+        match changes {
+            [Change::MoveNodes(moves)] => moves[0].2.x,
+            _ => panic!("unexpected changes {:?}", changes),
+        }
+    }
+
+    #[test]
+    fn empty_history() {
+        let mut history = History::default();
+
+        assert!(!history.can_undo());
+        assert!(!history.can_redo());
+        assert!(history.undo().is_empty());
+        assert!(history.redo().is_empty());
+
+        history.record(Vec::new());
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn undo_reverts_and_redo_reapplies() {
+        let mut history = History::default();
+        history.record(move_to(1.0));
+        history.record(move_to(2.0));
+
+        // Moving back to where the node was before
+        assert_eq!(destination(&history.undo()), 0.0);
+        assert!(history.can_redo());
+        assert_eq!(destination(&history.redo()), 2.0);
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn recording_clears_redo() {
+        let mut history = History::default();
+        history.record(move_to(1.0));
+        history.undo();
+
+        history.record(move_to(2.0));
+        assert!(!history.can_redo());
+        assert!(history.redo().is_empty());
+    }
+
+    #[test]
+    fn oldest_actions_are_dropped() {
+        let mut history = History::default();
+        for x in 0..HISTORY_LENGTH + 10 {
+            history.record(move_to(x as f32 + 1.0));
+        }
+
+        let mut undone = 0;
+        while history.can_undo() {
+            history.undo();
+            undone += 1;
+        }
+        assert_eq!(undone, HISTORY_LENGTH);
+
+        // The first action still in the history is the 11th one
+        assert_eq!(destination(&history.redo()), 11.0);
+    }
+}
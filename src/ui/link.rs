@@ -1,3 +1,5 @@
+use crate::pipewire_impl::{LinkProperties, MediaType, PortType};
+
 use super::Id;
 
 #[derive(Debug)]
@@ -9,6 +11,7 @@ pub struct Link {
     pub from_port: u32,
     pub to_port: u32,
     pub active: bool,
+    pub properties: LinkProperties,
 }
 
 impl Link {
@@ -16,3 +19,39 @@ impl Link {
         self.from_node == self.to_node
     }
 }
+
+/// Reasons for refusing a link drawn by the user
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkError {
+    /// Both ports are inputs, or both are outputs
+    SameDirection(PortType),
+    /// One of the ports doesn't report whether it is an input or an output
+    UnknownDirection,
+    MediaTypeMismatch(MediaType, MediaType),
+    Duplicate,
+    SelfLoop,
+    /// One of the ports was removed while the link was being drawn
+    PortGone,
+}
+
+impl LinkError {
+    pub fn describe(&self) -> String {
+        match self {
+            LinkError::SameDirection(PortType::Input) => "Can't link two input ports".to_string(),
+            LinkError::SameDirection(PortType::Output) => "Can't link two output ports".to_string(),
+            LinkError::SameDirection(PortType::Unknown) | LinkError::UnknownDirection => {
+                "Can't link ports of unknown direction".to_string()
+            }
+            LinkError::MediaTypeMismatch(from, to) => {
+                format!("Can't link a {:?} port to a {:?} port", from, to)
+            }
+            LinkError::Duplicate => "These ports are already linked".to_string(),
+            LinkError::SelfLoop => "Linking a node to itself needs a feedback link".to_string(),
+            LinkError::PortGone => "The port no longer exists".to_string(),
+        }
+    }
+    /// Whether the expert setting allows creating the link anyway
+    pub fn can_override(&self) -> bool {
+        matches!(self, LinkError::MediaTypeMismatch(..) | LinkError::SelfLoop)
+    }
+}
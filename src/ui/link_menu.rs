@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+
+use crate::pipewire_impl::{LinkOptions, LinkOrigin};
+
+use super::link::Link;
+
+/// Actions that can be performed on a link through its context menu
+pub enum LinkAction {
+    /// Recreate a link created by pw-viz with different options
+    Recreate {
+        link_id: u32,
+        from_port: u32,
+        to_port: u32,
+        options: LinkOptions,
+    },
+    Remove(u32),
+}
+
+/// Context menu shown when right clicking on a link
+#[derive(Default)]
+pub struct LinkMenu {
+    target: Option<(u32, egui::Pos2)>,
+    // The click which opened the menu shouldn't close it again
+    just_opened: bool,
+}
+
+impl LinkMenu {
+    pub fn open(&mut self, link: u32, position: egui::Pos2) {
+        self.target = Some((link, position));
+        self.just_opened = true;
+    }
+    pub fn show(&mut self, ctx: &egui::Context, links: &HashMap<u32, Link>) -> Option<LinkAction> {
+        let (link_id, position) = self.target?;
+
+        let link = match links.get(&link_id) {
+            Some(link) => link,
+            None => {
+                // The link was removed while the menu was open
+                self.target = None;
+                return None;
+            }
+        };
+
+        let mut action = None;
+
+        let response = egui::Area::new("link_context_menu")
+            .order(egui::Order::Foreground)
+            .fixed_pos(position)
+            .show(ctx, |ui| {
+                egui::Frame::menu(ui.style()).show(ui, |ui| {
+                    ui.strong(format!("Link {}", link.id));
+                    ui.label(match link.properties.origin {
+                        LinkOrigin::Owned => "Created by pw-viz, removed when it exits",
+                        LinkOrigin::Lingering => "Created by pw-viz, kept after it exits",
+                        LinkOrigin::External => "Created by another client",
+                    });
+                    ui.separator();
+
+                    // Options of other links are left to whoever created them
+                    if link.properties.origin.created_by_pw_viz() {
+                        let mut options = link.properties.options();
+
+                        let changed = [
+                            ui.checkbox(&mut options.linger, "Keep after pw-viz exits"),
+                            ui.checkbox(&mut options.passive, "Passive")
+                                .on_hover_text("Don't keep the nodes running"),
+                            ui.checkbox(&mut options.feedback, "Feedback")
+                                .on_hover_text("Allow the link to form a loop"),
+                        ]
+                        .iter()
+                        .any(|response| response.changed());
+
+                        if changed {
+                            action = Some(LinkAction::Recreate {
+                                link_id,
+                                from_port: link.from_port,
+                                to_port: link.to_port,
+                                options,
+                            });
+                        }
+                    }
+
+                    if ui.button("Remove").clicked() {
+                        action = Some(LinkAction::Remove(link_id));
+                    }
+                });
+            })
+            .response;
+
+        if action.is_some() || (response.clicked_elsewhere() && !self.just_opened) {
+            self.target = None;
+        }
+        self.just_opened = false;
+
+        action
+    }
+}
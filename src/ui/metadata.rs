@@ -0,0 +1,211 @@
+use std::collections::BTreeMap;
+
+/// Changes to metadata requested through the browser
+pub enum MetadataAction {
+    Set {
+        metadata: String,
+        subject: u32,
+        key: String,
+        type_: Option<String>,
+        value: Option<String>,
+    },
+    Clear {
+        metadata: String,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    type_: Option<String>,
+    value: String,
+}
+
+/// Properties of a metadata object, keyed by subject and key
+#[derive(Debug, Default)]
+struct Metadata {
+    id: u32,
+    entries: BTreeMap<(u32, String), Entry>,
+}
+
+/// Entry being added or edited in the browser
+#[derive(Debug, Default)]
+struct EntryForm {
+    subject: u32,
+    key: String,
+    type_: String,
+    value: String,
+}
+
+/// All metadata objects along with their properties
+#[derive(Default)]
+pub struct MetadataBrowser {
+    objects: BTreeMap<String, Metadata>, //metadata.name to Metadata
+    selected: Option<String>,
+    form: EntryForm,
+    /// Metadata object whose "Clear all" was clicked, waiting for confirmation
+    confirm_clear: Option<String>,
+}
+
+impl MetadataBrowser {
+    pub fn add(&mut self, name: String, id: u32) {
+        self.objects.entry(name).or_default().id = id;
+    }
+    pub fn remove(&mut self, name: &str) {
+        self.objects.remove(name);
+    }
+    /// A `None` key removes all properties of the subject
+    pub fn update(
+        &mut self,
+        metadata: String,
+        subject: u32,
+        key: Option<String>,
+        type_: Option<String>,
+        value: Option<String>,
+    ) {
+        let entries = &mut self.objects.entry(metadata).or_default().entries;
+
+        match (key, value) {
+            (Some(key), Some(value)) => {
+                entries.insert((subject, key), Entry { type_, value });
+            }
+            (Some(key), None) => {
+                entries.remove(&(subject, key));
+            }
+            (None, _) => entries.retain(|(entry_subject, _), _| *entry_subject != subject),
+        }
+    }
+    /// Lists the properties of the selected metadata object with an editor below, returns the change picked by the user
+    pub fn draw(&mut self, ui: &mut egui::Ui) -> Option<MetadataAction> {
+        let mut action = None;
+
+        if self
+            .selected
+            .as_ref()
+            .map_or(true, |selected| !self.objects.contains_key(selected))
+        {
+            self.selected = self.objects.keys().next().cloned();
+        }
+
+        let name = match self.selected.clone() {
+            Some(name) => name,
+            None => {
+                ui.label("No metadata objects");
+                return None;
+            }
+        };
+
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source("metadata_object")
+                .selected_text(&name)
+                .show_ui(ui, |ui| {
+                    for (object_name, object) in self.objects.iter() {
+                        ui.selectable_value(
+                            &mut self.selected,
+                            Some(object_name.clone()),
+                            format!("{} [{}]", object_name, object.id),
+                        );
+                    }
+                });
+
+            // Clearing can't be undone, it takes a second click
+            if self.confirm_clear.as_ref() == Some(&name) {
+                ui.label("Remove every property?");
+                if ui.button("Clear").clicked() {
+                    self.confirm_clear = None;
+                    action = Some(MetadataAction::Clear {
+                        metadata: name.clone(),
+                    });
+                }
+                if ui.button("Cancel").clicked() {
+                    self.confirm_clear = None;
+                }
+            } else if ui.button("Clear all").clicked() {
+                self.confirm_clear = Some(name.clone());
+            }
+        });
+        ui.separator();
+
+        let entries = &self.objects[&name].entries;
+        let form = &mut self.form;
+
+        egui::ScrollArea::vertical()
+            .max_height(300.0)
+            .show(ui, |ui| {
+                egui::Grid::new("metadata_entries")
+                    .num_columns(5)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.strong("Subject");
+                        ui.strong("Key");
+                        ui.strong("Type");
+                        ui.strong("Value");
+                        ui.end_row();
+
+                        for ((subject, key), entry) in entries.iter() {
+                            ui.label(subject.to_string());
+                            ui.label(key);
+                            ui.label(entry.type_.as_deref().unwrap_or_default());
+                            ui.label(&entry.value);
+
+                            ui.horizontal(|ui| {
+                                if ui.small_button("✏").on_hover_text("Edit").clicked() {
+                                    *form = EntryForm {
+                                        subject: *subject,
+                                        key: key.clone(),
+                                        type_: entry.type_.clone().unwrap_or_default(),
+                                        value: entry.value.clone(),
+                                    };
+                                }
+                                if ui.small_button("🗑").on_hover_text("Remove").clicked() {
+                                    action = Some(MetadataAction::Set {
+                                        metadata: name.clone(),
+                                        subject: *subject,
+                                        key: key.clone(),
+                                        type_: None,
+                                        value: None,
+                                    });
+                                }
+                            });
+                            ui.end_row();
+                        }
+                    });
+            });
+        ui.separator();
+
+        egui::Grid::new("metadata_form")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Subject");
+                ui.add(egui::DragValue::new(&mut form.subject));
+                ui.end_row();
+
+                ui.label("Key");
+                ui.text_edit_singleline(&mut form.key);
+                ui.end_row();
+
+                ui.label("Type");
+                ui.text_edit_singleline(&mut form.type_)
+                    .on_hover_text("Optional, like Spa:String:JSON");
+                ui.end_row();
+
+                ui.label("Value");
+                ui.text_edit_singleline(&mut form.value);
+                ui.end_row();
+            });
+
+        if ui
+            .add_enabled(!form.key.is_empty(), egui::Button::new("Set"))
+            .clicked()
+        {
+            action = Some(MetadataAction::Set {
+                metadata: name,
+                subject: form.subject,
+                key: form.key.clone(),
+                type_: Some(form.type_.clone()).filter(|type_| !type_.is_empty()),
+                value: Some(form.value.clone()),
+            });
+        }
+
+        action
+    }
+}
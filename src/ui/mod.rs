@@ -1,18 +1,44 @@
+mod alias;
+mod arrange;
+mod client;
+mod device;
+mod format;
 mod graph;
+mod history;
 mod id;
 mod link;
+mod link_menu;
+mod metadata;
 mod node;
+mod node_menu;
 mod port;
+mod profiler;
+mod settings;
+mod virtual_device;
 
-use crate::pipewire_impl::PipewireMessage;
+use crate::pipewire_impl::{
+    DefaultKind, LinkOptions, LinkOrigin, MediaType, PipewireMessage, VirtualDevice,
+    DEFAULT_METADATA, SETTINGS_METADATA,
+};
 
 use pipewire::channel::Sender;
 use serde::{Deserialize, Serialize};
-use std::sync::mpsc::Receiver;
+use std::{collections::HashMap, sync::mpsc::Receiver};
 
+use arrange::Arrangement;
+use client::Client;
+use device::{Device, DeviceAction};
 use graph::Graph;
+use history::{Change, History};
 use id::Id;
+use link_menu::LinkAction;
+use metadata::{MetadataAction, MetadataBrowser};
+use node::PwNode;
+use node_menu::NodeAction;
 use port::Port;
+use profiler::ProfilerView;
+use settings::ClockSettings;
+use virtual_device::VirtualDeviceDialog;
 
 pub const INITIAL_WIDTH: u32 = 1280;
 pub const INITIAL_HEIGHT: u32 = 720;
@@ -20,11 +46,58 @@ pub const INITIAL_HEIGHT: u32 = 720;
 #[derive(Debug)]
 pub enum UiMessage {
     RemoveLink(u32),
-    AddLink { from_port: u32, to_port: u32 },
+    AddLink {
+        from_port: u32,
+        to_port: u32,
+        options: LinkOptions,
+        origin: LinkOrigin,
+    },
+    /// Create a new link, then remove the old one if that succeeded
+    ReplaceLink {
+        old: u32,
+        from_port: u32,
+        to_port: u32,
+        options: LinkOptions,
+        origin: LinkOrigin,
+    },
+    SetDefaultNode {
+        kind: DefaultKind,
+        pw_name: String,
+    },
+    SetStreamTarget {
+        node_id: u32,
+        target: Option<String>,
+    },
+    SetMetadataProperty {
+        /// metadata.name of the metadata object
+        metadata: String,
+        subject: u32,
+        key: String,
+        type_: Option<String>,
+        /// `None` removes the property
+        value: Option<String>,
+    },
+    ClearMetadata {
+        /// metadata.name of the metadata object
+        metadata: String,
+    },
+    SetDeviceProfile {
+        device_id: u32,
+        index: i32,
+    },
+    SetDeviceRoute {
+        device_id: u32,
+        /// Index of the card device the route belongs to
+        device: i32,
+        index: i32,
+    },
+    CreateVirtualDevice(VirtualDevice),
+    DestroyNode(u32),
     Exit,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Theme {
     titlebar: egui::Color32,
     titlebar_hovered: egui::Color32,
@@ -38,6 +111,15 @@ pub struct Theme {
     midi_port: egui::Color32,
     midi_port_hovered: egui::Color32,
 
+    format_mismatch: egui::Color32,
+    warning: egui::Color32,
+
+    client_group: egui::Color32,
+    highlight: egui::Color32,
+
+    owned_link: egui::Color32,
+    lingering_link: egui::Color32,
+
     text_color: egui::Color32,
 
     node_background: egui::Color32,
@@ -59,6 +141,15 @@ impl Default for Theme {
             midi_port: egui::Color32::RED,
             midi_port_hovered: egui::Color32::LIGHT_RED,
 
+            format_mismatch: egui::Color32::from_rgba_unmultiplied(230, 160, 40, 255),
+            warning: egui::Color32::YELLOW,
+
+            client_group: egui::Color32::from_rgba_unmultiplied(140, 140, 140, 255),
+            highlight: egui::Color32::from_rgba_unmultiplied(120, 180, 255, 255),
+
+            owned_link: egui::Color32::from_rgba_unmultiplied(240, 200, 90, 255),
+            lingering_link: egui::Color32::from_rgba_unmultiplied(90, 200, 230, 255),
+
             text_color: egui::Color32::WHITE,
             node_background: egui::Color32::from_rgba_unmultiplied(50, 50, 50, 255),
             node_background_hovered: egui::Color32::from_rgba_unmultiplied(75, 75, 75, 255),
@@ -66,14 +157,39 @@ impl Default for Theme {
     }
 }
 
+impl Theme {
+    /// Colors of a port and the links attached to it, based on its media type
+    pub fn port_colors(&self, media_type: Option<MediaType>) -> (egui::Color32, egui::Color32) {
+        match media_type {
+            Some(MediaType::Audio) => (self.audio_port, self.audio_port_hovered),
+            Some(MediaType::Video) => (self.video_port, self.video_port_hovered),
+            Some(MediaType::Midi) => (self.midi_port, self.midi_port_hovered),
+            None => (egui::Color32::GRAY, egui::Color32::LIGHT_GRAY),
+        }
+    }
+}
+
 pub struct GraphUI {
     graph: Graph,
+    devices: HashMap<u32, Device>, //Device id to Device
+    profiler: ProfilerView,
+    clock_settings: ClockSettings,
+    metadata: MetadataBrowser,
+    virtual_device: VirtualDeviceDialog,
+    history: History,
     pipewire_receiver: Receiver<PipewireMessage>,
     pipewire_sender: Sender<UiMessage>,
     theme: Theme,
     show_theme: bool,
     show_about: bool,
     show_controls: bool,
+    show_inspector: bool,
+    show_devices: bool,
+    show_clients: bool,
+    show_profiler: bool,
+    show_clock_settings: bool,
+    show_metadata: bool,
+    show_virtual_device: bool,
 }
 
 impl GraphUI {
@@ -83,12 +199,25 @@ impl GraphUI {
     ) -> Self {
         GraphUI {
             graph: Graph::new(),
+            devices: HashMap::new(),
+            profiler: ProfilerView::default(),
+            clock_settings: ClockSettings::default(),
+            metadata: MetadataBrowser::default(),
+            virtual_device: VirtualDeviceDialog::default(),
+            history: History::default(),
             pipewire_receiver,
             pipewire_sender,
             theme: Theme::default(),
             show_theme: false,
             show_about: false,
             show_controls: false,
+            show_inspector: false,
+            show_devices: false,
+            show_clients: false,
+            show_profiler: false,
+            show_clock_settings: false,
+            show_metadata: false,
+            show_virtual_device: false,
         }
     }
 
@@ -131,6 +260,30 @@ impl GraphUI {
                     ui.color_edit_button_srgba(&mut theme.midi_port_hovered);
                     ui.end_row();
 
+                    ui.label("Format mismatch");
+                    ui.color_edit_button_srgba(&mut theme.format_mismatch);
+                    ui.end_row();
+
+                    ui.label("Warning");
+                    ui.color_edit_button_srgba(&mut theme.warning);
+                    ui.end_row();
+
+                    ui.label("Client group");
+                    ui.color_edit_button_srgba(&mut theme.client_group);
+                    ui.end_row();
+
+                    ui.label("Highlight");
+                    ui.color_edit_button_srgba(&mut theme.highlight);
+                    ui.end_row();
+
+                    ui.label("Link owned by pw-viz");
+                    ui.color_edit_button_srgba(&mut theme.owned_link);
+                    ui.end_row();
+
+                    ui.label("Lingering link created by pw-viz");
+                    ui.color_edit_button_srgba(&mut theme.lingering_link);
+                    ui.end_row();
+
                     ui.label("Node background");
                     ui.color_edit_button_srgba(&mut theme.node_background);
                     ui.end_row();
@@ -183,34 +336,353 @@ impl GraphUI {
                     ui.label("Move nodes, create links between nodes");
                     ui.end_row();
 
+                    ui.label("Left Click + Drag on the canvas");
+                    ui.label("Select the nodes inside the box, dragging any selected node moves them all");
+                    ui.end_row();
+
+                    ui.label("Ctrl + Left Click on a node");
+                    ui.label("Add the node to the selection, or remove it");
+                    ui.end_row();
+
                     ui.label("Alt + Left Click + Drag");
                     ui.label("Remove Links");
                     ui.end_row();
 
+                    ui.label("Left Click on a link");
+                    ui.label("Select the link, hold Shift to select several");
+                    ui.end_row();
+
+                    ui.label("Left Click + Drag on a link end");
+                    ui.label("Re-route the link to another port");
+                    ui.end_row();
+
+                    ui.label("Delete / Backspace");
+                    ui.label("Remove the selected links");
+                    ui.end_row();
+
+                    ui.label("Ctrl + Z / Ctrl + Shift + Z");
+                    ui.label("Undo / redo changes to links, node positions and virtual devices");
+                    ui.end_row();
+
                     ui.label("Middle Mouse + Drag");
                     ui.label("Pan the graph");
                     ui.end_row();
 
-                    ui.label("Ctrl");
-                    ui.label("Show pipewire ids of nodes and ports");
+                    ui.label("Right Click on a node");
+                    ui.label("Open the node menu: inspect, rename (aliases are kept across restarts), collapse or hide the node, rename its ports, set default sink/source, move streams, connect or disconnect all channels, copy its name or id");
+                    ui.end_row();
+
+                    ui.label("Right Click on a link");
+                    ui.label(
+                        "Open the link menu, change its lifetime and passive/feedback options",
+                    );
+                    ui.end_row();
+
+                    ui.label("Shift + drop a link");
+                    ui.label("Create a passive link, drawn thinner than the others");
+                    ui.end_row();
+
+                    ui.label("F12");
+                    ui.label("Show or hide pipewire ids of nodes and ports");
                     ui.end_row();
                 })
             });
     }
 
+    fn devices_window(&mut self, ctx: &egui::Context, _ui: &mut egui::Ui) {
+        let graph = &self.graph;
+        let devices = &self.devices;
+        let sender = &self.pipewire_sender;
+        egui::Window::new("Devices")
+            .open(&mut self.show_devices)
+            .resizable(true)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    let mut devices = devices.values().collect::<Vec<_>>();
+                    devices.sort_by(|a, b| natord::compare(a.name(), b.name()));
+
+                    if devices.is_empty() {
+                        ui.label("No devices");
+                    }
+
+                    for device in devices {
+                        let nodes = graph.nodes_of_device(device.id());
+
+                        let message = match device.draw(ui, &nodes) {
+                            Some(DeviceAction::SetProfile(index)) => UiMessage::SetDeviceProfile {
+                                device_id: device.id(),
+                                index,
+                            },
+                            Some(DeviceAction::SetRoute {
+                                device: card_device,
+                                index,
+                            }) => UiMessage::SetDeviceRoute {
+                                device_id: device.id(),
+                                device: card_device,
+                                index,
+                            },
+                            None => continue,
+                        };
+
+                        sender.send(message).expect("Failed to send ui message");
+                    }
+                });
+            });
+    }
+
+    fn clients_window(&mut self, ctx: &egui::Context, _ui: &mut egui::Ui) {
+        let graph = &mut self.graph;
+        egui::Window::new("Clients")
+            .open(&mut self.show_clients)
+            .resizable(true)
+            .show(ctx, |ui| {
+                ui.checkbox(&mut graph.show_client_groups, "Group nodes by client");
+                ui.separator();
+
+                let mut highlighted = graph.highlighted_client;
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for client in graph.clients() {
+                        let nodes = graph.nodes_of_client(client.id());
+                        let selected = highlighted == Some(client.id());
+
+                        let response = ui
+                            .selectable_label(
+                                selected,
+                                format!("{} ({} nodes)", client.name(), nodes.len()),
+                            )
+                            .on_hover_text(client.describe());
+
+                        if response.clicked() {
+                            highlighted = if selected { None } else { Some(client.id()) };
+                        }
+
+                        if selected {
+                            ui.indent(("client_nodes", client.id()), |ui| {
+                                for node in nodes {
+                                    ui.label(node.display_name());
+                                }
+                            });
+                        }
+                    }
+                });
+
+                graph.highlighted_client = highlighted;
+            });
+    }
+
+    fn profiler_window(&mut self, ctx: &egui::Context, _ui: &mut egui::Ui) {
+        let profiler = &self.profiler;
+        let theme = &self.theme;
+        egui::Window::new("Profiler")
+            .open(&mut self.show_profiler)
+            .resizable(true)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| profiler.draw(ui, theme));
+            });
+    }
+
+    fn clock_settings_window(&mut self, ctx: &egui::Context, _ui: &mut egui::Ui) {
+        let clock_settings = &mut self.clock_settings;
+        let theme = &self.theme;
+        let sender = &self.pipewire_sender;
+        egui::Window::new("Clock settings")
+            .open(&mut self.show_clock_settings)
+            .resizable(false)
+            .show(ctx, |ui| {
+                for (key, value) in clock_settings.draw(ui, theme) {
+                    sender
+                        .send(UiMessage::SetMetadataProperty {
+                            metadata: SETTINGS_METADATA.to_string(),
+                            subject: 0,
+                            key,
+                            type_: None,
+                            value: Some(value),
+                        })
+                        .expect("Failed to send ui message");
+                }
+            });
+    }
+
+    fn metadata_window(&mut self, ctx: &egui::Context, _ui: &mut egui::Ui) {
+        let metadata = &mut self.metadata;
+        let sender = &self.pipewire_sender;
+        egui::Window::new("Metadata")
+            .open(&mut self.show_metadata)
+            .resizable(true)
+            .show(ctx, |ui| {
+                let message = match metadata.draw(ui) {
+                    Some(MetadataAction::Set {
+                        metadata,
+                        subject,
+                        key,
+                        type_,
+                        value,
+                    }) => UiMessage::SetMetadataProperty {
+                        metadata,
+                        subject,
+                        key,
+                        type_,
+                        value,
+                    },
+                    Some(MetadataAction::Clear { metadata }) => {
+                        UiMessage::ClearMetadata { metadata }
+                    }
+                    None => return,
+                };
+
+                sender.send(message).expect("Failed to send ui message");
+            });
+    }
+
+    fn virtual_device_window(&mut self, ctx: &egui::Context, _ui: &mut egui::Ui) {
+        let dialog = &mut self.virtual_device;
+        let mut created = None;
+        egui::Window::new("New virtual device")
+            .open(&mut self.show_virtual_device)
+            .resizable(false)
+            .show(ctx, |ui| {
+                created = dialog.draw(ui);
+            });
+
+        if let Some(device) = created {
+            self.perform(vec![Change::CreateVirtualDevice(device)]);
+            self.show_virtual_device = false;
+        }
+    }
+
+    /// Applies changes made by the user and records them so they can be undone
+    fn perform(&mut self, changes: Vec<Change>) {
+        for change in changes.iter() {
+            self.apply(change);
+        }
+        self.history.record(changes);
+    }
+    fn undo(&mut self) {
+        for change in self.history.undo() {
+            self.apply(&change);
+        }
+    }
+    fn redo(&mut self) {
+        for change in self.history.redo() {
+            self.apply(&change);
+        }
+    }
+    /// Sends the messages making a change to the graph
+    ///
+    /// The ids recorded with the change are used while they are still valid,
+    /// ports are only looked up by name once their nodes were recreated
+    fn apply(&mut self, change: &Change) {
+        let message = match change {
+            Change::AddLink(link) => {
+                self.graph
+                    .resolve_link(link)
+                    .map(|(from_port, to_port)| UiMessage::AddLink {
+                        from_port,
+                        to_port,
+                        options: link.options,
+                        origin: link.origin,
+                    })
+            }
+            Change::RemoveLink(link) => self.graph.resolve_link_id(link).map(UiMessage::RemoveLink),
+            Change::ReplaceLink { old, new } => {
+                let old_id = self.graph.resolve_link_id(old);
+
+                old_id
+                    .zip(self.graph.resolve_link(new))
+                    .map(|(old, (from_port, to_port))| UiMessage::ReplaceLink {
+                        old,
+                        from_port,
+                        to_port,
+                        options: new.options,
+                        origin: new.origin,
+                    })
+            }
+            Change::MoveNodes(moves) => {
+                for &(node_id, _, position) in moves {
+                    self.graph.set_node_position(node_id, position);
+                }
+                return;
+            }
+            Change::CreateVirtualDevice(device) => {
+                Some(UiMessage::CreateVirtualDevice(device.clone()))
+            }
+            Change::RemoveVirtualDevice(device) => self
+                .graph
+                .find_pw_node(&device.node_name())
+                .map(UiMessage::DestroyNode),
+        };
+
+        match message {
+            Some(message) => self
+                .pipewire_sender
+                .send(message)
+                .expect("Failed to send ui message"),
+            None => log::warn!("Couldn't apply {:?}, its objects are gone", change),
+        }
+    }
+
+    fn inspector_window(&mut self, ctx: &egui::Context, _ui: &mut egui::Ui) {
+        let graph = &self.graph;
+        let theme = &self.theme;
+        egui::Window::new("Inspector")
+            .open(&mut self.show_inspector)
+            .resizable(true)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    let mut malformed = graph
+                        .nodes_with_unknown_ports()
+                        .map(|node| node.display_name())
+                        .collect::<Vec<_>>();
+
+                    if !malformed.is_empty() {
+                        malformed.sort_by(|a, b| natord::compare(a, b));
+
+                        ui.colored_label(theme.warning, "⚠ Nodes with ports of unknown direction");
+                        for name in malformed {
+                            ui.label(name);
+                        }
+                        ui.separator();
+                    }
+
+                    match graph.inspected_node() {
+                        Some(node) => node.inspect(ui, theme),
+                        None => {
+                            ui.label("Select a node to inspect it");
+                        }
+                    }
+                });
+            });
+    }
+
     /// Update the graph ui based on the message sent by the pipewire thread
     fn process_message(&mut self, message: PipewireMessage) {
         match message {
             PipewireMessage::NodeAdded {
                 id,
                 name,
+                pw_name,
                 description,
+                media_class,
                 media_type,
+                device_id,
+                client_id,
             } => {
-                self.graph.add_node(name, id, description, media_type);
+                let pw_node = PwNode::new(
+                    id,
+                    pw_name,
+                    description,
+                    media_class,
+                    media_type,
+                    device_id,
+                    client_id,
+                );
+
+                self.graph.add_node(name, pw_node);
             }
             PipewireMessage::NodeRemoved { name, id } => {
                 self.graph.remove_node(&name, id);
+                self.profiler.remove_driver(id);
             }
 
             PipewireMessage::PortAdded {
@@ -219,8 +691,11 @@ impl GraphUI {
                 id,
                 name,
                 port_type,
+                media_type,
+                channel,
+                flags,
             } => {
-                let port = Port::new(id, name, port_type);
+                let port = Port::new(id, name, port_type, media_type, channel, flags);
 
                 self.graph.add_port(node_name, node_id, port);
             }
@@ -231,9 +706,16 @@ impl GraphUI {
                 to_node_name,
                 from_port,
                 to_port,
+                properties,
             } => {
-                self.graph
-                    .add_link(id, from_node_name, to_node_name, from_port, to_port);
+                self.graph.add_link(
+                    id,
+                    from_node_name,
+                    to_node_name,
+                    from_port,
+                    to_port,
+                    properties,
+                );
             }
             PipewireMessage::PortRemoved {
                 node_name,
@@ -246,6 +728,99 @@ impl GraphUI {
                 self.graph.remove_link(id);
             }
             PipewireMessage::LinkStateChanged { id: _, active: _ } => {}
+            PipewireMessage::NodeFormatChanged { name, id, update } => {
+                self.graph.update_node_format(&name, id, update);
+            }
+            PipewireMessage::NodeSchedulingChanged {
+                name,
+                id,
+                scheduling,
+            } => {
+                self.graph.update_node_scheduling(&name, id, scheduling);
+            }
+            PipewireMessage::PortFormatChanged {
+                node_name,
+                node_id,
+                id,
+                update,
+            } => {
+                self.graph
+                    .update_port_format(&node_name, node_id, id, update);
+            }
+            PipewireMessage::DefaultNodeChanged {
+                kind,
+                configured,
+                pw_name,
+            } => {
+                self.graph.defaults.set(kind, configured, pw_name);
+            }
+            PipewireMessage::StreamTargetChanged { id, target } => match target {
+                Some(target) => {
+                    self.graph.stream_targets.insert(id, target);
+                }
+                None => {
+                    self.graph.stream_targets.remove(&id);
+                }
+            },
+            PipewireMessage::MetadataRemoved { name, id: _ } => {
+                self.metadata.remove(&name);
+
+                if name == DEFAULT_METADATA {
+                    self.graph.defaults = Default::default();
+                    self.graph.stream_targets.clear();
+                } else if name == SETTINGS_METADATA {
+                    self.clock_settings.clear();
+                }
+            }
+            PipewireMessage::DeviceAdded { id, name, pw_name } => {
+                self.devices.insert(id, Device::new(id, name, pw_name));
+            }
+            PipewireMessage::DeviceChanged { id, update } => match self.devices.get_mut(&id) {
+                Some(device) => device.update(update),
+                None => log::warn!("Device with id: {} was never added", id),
+            },
+            PipewireMessage::DeviceRemoved { id } => {
+                self.devices.remove(&id);
+            }
+            PipewireMessage::ClientAdded {
+                id,
+                name,
+                pid,
+                binary,
+            } => {
+                self.graph.add_client(Client::new(id, name, pid, binary));
+            }
+            PipewireMessage::ClientRemoved { id } => {
+                self.graph.remove_client(id);
+            }
+            PipewireMessage::Profile(sample) => {
+                for profile in sample.drivers.iter() {
+                    self.graph
+                        .driver_clocks
+                        .insert(profile.driver.id, (profile.quantum, profile.rate));
+                }
+                self.profiler.update(sample);
+            }
+            PipewireMessage::ProfilerRemoved => {
+                self.graph.driver_clocks.clear();
+                self.profiler.clear();
+            }
+            PipewireMessage::MetadataAdded { name, id } => {
+                self.metadata.add(name, id);
+            }
+            PipewireMessage::MetadataPropertyChanged {
+                metadata,
+                subject,
+                key,
+                type_,
+                value,
+            } => {
+                if metadata == SETTINGS_METADATA && subject == 0 {
+                    self.clock_settings.update(key.clone(), value.clone());
+                }
+
+                self.metadata.update(metadata, subject, key, type_, value);
+            }
         };
     }
 
@@ -282,23 +857,164 @@ impl eframe::App for GraphUI {
     /// Note that you must enable the `persistence` feature for this to work.
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
         eframe::set_value(storage, "theme", &self.theme);
+        eframe::set_value(storage, "port_filter", &self.graph.port_filter);
+        eframe::set_value(storage, "client_groups", &self.graph.show_client_groups);
+        eframe::set_value(storage, "driver_groups", &self.graph.show_driver_groups);
+        eframe::set_value(storage, "link_options", &self.graph.link_options);
+        eframe::set_value(storage, "snap_to_grid", &self.graph.snap_to_grid);
+        eframe::set_value(storage, "aliases", &self.graph.aliases);
+        eframe::set_value(storage, "invalid_links", &self.graph.allow_invalid_links);
     }
 
     /// Called each time the UI needs repainting, which may be many times per second.
     fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
         self.pump_messages();
 
+        // Text fields have their own undo
+        if !ctx.wants_keyboard_input() {
+            let (undo, redo) = {
+                let input = ctx.input();
+                let z = input.modifiers.ctrl && input.key_pressed(egui::Key::Z);
+
+                (z && !input.modifiers.shift, z && input.modifiers.shift)
+            };
+
+            if undo {
+                self.undo();
+            } else if redo {
+                self.redo();
+            }
+        }
+
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
                 egui::menu::menu_button(ui, "File", |ui| {
+                    if ui.button("New virtual device…").clicked() {
+                        self.show_virtual_device = true;
+                    }
                     if ui.button("Quit").clicked() {
                         frame.close();
                     }
                 });
+                egui::menu::menu_button(ui, "Edit", |ui| {
+                    if ui
+                        .add_enabled(self.history.can_undo(), egui::Button::new("Undo"))
+                        .clicked()
+                    {
+                        self.undo();
+                        ui.close_menu();
+                    }
+                    if ui
+                        .add_enabled(self.history.can_redo(), egui::Button::new("Redo"))
+                        .clicked()
+                    {
+                        self.redo();
+                        ui.close_menu();
+                    }
+                });
+                egui::menu::menu_button(ui, "View", |ui| {
+                    let port_filter = &mut self.graph.port_filter;
+
+                    ui.checkbox(&mut port_filter.show_monitor, "👁 Monitor ports");
+                    ui.checkbox(&mut port_filter.show_physical, "🔌 Physical ports");
+                    ui.checkbox(&mut port_filter.show_terminal, "⏹ Terminal ports");
+
+                    ui.separator();
+
+                    ui.checkbox(&mut self.graph.show_driver_groups, "⏱ Driver groups");
+
+                    let hidden = self.graph.hidden_nodes();
+                    if ui
+                        .add_enabled(
+                            hidden > 0,
+                            egui::Button::new(format!("Show hidden nodes ({})", hidden)),
+                        )
+                        .clicked()
+                    {
+                        self.graph.show_hidden_nodes();
+                        ui.close_menu();
+                    }
+
+                    ui.separator();
+
+                    if ui.button("Inspector").clicked() {
+                        self.show_inspector = true;
+                    }
+                    if ui.button("Devices").clicked() {
+                        self.show_devices = true;
+                    }
+                    if ui.button("Clients").clicked() {
+                        self.show_clients = true;
+                    }
+                    if ui.button("Profiler").clicked() {
+                        self.show_profiler = true;
+                    }
+                    if ui.button("Metadata").clicked() {
+                        self.show_metadata = true;
+                    }
+                });
+                egui::menu::menu_button(ui, "Arrange", |ui| {
+                    let selected = self.graph.selected_nodes().len();
+
+                    for arrangement in Arrangement::ALL {
+                        if ui
+                            .add_enabled(
+                                selected >= arrangement.min_nodes(),
+                                egui::Button::new(arrangement.label()),
+                            )
+                            .on_disabled_hover_text(format!(
+                                "Select at least {} nodes",
+                                arrangement.min_nodes()
+                            ))
+                            .clicked()
+                        {
+                            self.graph.arrange_selection(arrangement);
+                            ui.close_menu();
+                        }
+                    }
+
+                    ui.separator();
+
+                    ui.checkbox(&mut self.graph.snap_to_grid, "Snap to grid");
+                });
+                egui::menu::menu_button(ui, "Links", |ui| {
+                    ui.checkbox(
+                        &mut self.graph.link_options.linger,
+                        "Keep new links after pw-viz exits",
+                    );
+                    ui.checkbox(&mut self.graph.link_options.passive, "Passive")
+                        .on_hover_text("New links don't keep their nodes running");
+                    ui.checkbox(&mut self.graph.link_options.feedback, "Feedback")
+                        .on_hover_text("New links can form loops");
+
+                    ui.separator();
+
+                    ui.checkbox(&mut self.graph.allow_invalid_links, "Allow invalid links")
+                        .on_hover_text(
+                            "Link ports of different media types, or a node to itself, anyway",
+                        );
+                });
                 egui::menu::menu_button(ui, "Settings", |ui| {
                     if ui.button("Theme").clicked() {
                         self.show_theme = true;
                     }
+                    if ui.button("Clock").clicked() {
+                        self.show_clock_settings = true;
+                    }
+
+                    ui.separator();
+
+                    if ui
+                        .add_enabled(
+                            !self.graph.aliases.is_empty(),
+                            egui::Button::new("Forget all aliases"),
+                        )
+                        .on_hover_text("Nodes and ports get their own names back")
+                        .clicked()
+                    {
+                        self.graph.clear_aliases();
+                        ui.close_menu();
+                    }
                 });
                 egui::menu::menu_button(ui, "Help", |ui| {
                     if ui.button("Controls").clicked() {
@@ -311,25 +1027,132 @@ impl eframe::App for GraphUI {
             });
         });
 
+        egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                self.profiler.status(ui, &self.theme);
+            });
+        });
+
+        // Profiler data keeps coming in without any user input, the status bar is refreshed less often than the window
+        if self.profiler.is_receiving() {
+            let interval = if self.show_profiler { 100 } else { 1000 };
+            ctx.request_repaint_after(std::time::Duration::from_millis(interval));
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| {
             // If any new links were created/removed, notify the pipewire thread
-            if let Some(link_update) = self.graph.draw(ctx, ui, &self.theme) {
-                match link_update {
+            let mut changes = Vec::new();
+            for link_update in self.graph.draw(ctx, ui, &self.theme) {
+                let change = match link_update {
                     graph::LinkUpdate::Created {
                         from_port,
                         to_port,
                         from_node: _,
                         to_node: _,
+                        options,
+                    } => self
+                        .graph
+                        .link_ref(from_port, to_port, options)
+                        .map(Change::AddLink),
+                    graph::LinkUpdate::Removed(link_id) => {
+                        self.graph.link_ref_of(link_id).map(Change::RemoveLink)
+                    }
+                    graph::LinkUpdate::Replaced {
+                        old,
+                        from_port,
+                        to_port,
+                        options,
+                    } => self
+                        .graph
+                        .link_ref_of(old)
+                        .zip(self.graph.link_ref(from_port, to_port, options))
+                        .map(|(old, new)| Change::ReplaceLink { old, new }),
+                };
+                changes.extend(change);
+            }
+            self.perform(changes);
+
+            let moves = self.graph.take_node_moves();
+            if !moves.is_empty() {
+                // The nodes are already where the user left them
+                self.history.record(vec![Change::MoveNodes(moves)]);
+            }
+
+            if let Some(action) = self.graph.link_menu(ctx) {
+                let changes = match action {
+                    LinkAction::Recreate {
+                        link_id,
+                        from_port,
+                        to_port,
+                        options,
                     } => {
+                        // The properties of a link are fixed once created, so it is replaced
+                        let removed = self.graph.link_ref_of(link_id).map(Change::RemoveLink);
+                        let added = self
+                            .graph
+                            .link_ref(from_port, to_port, options)
+                            .map(Change::AddLink);
+
+                        removed.into_iter().chain(added).collect()
+                    }
+                    LinkAction::Remove(link_id) => self
+                        .graph
+                        .link_ref_of(link_id)
+                        .map(Change::RemoveLink)
+                        .into_iter()
+                        .collect(),
+                };
+                self.perform(changes);
+            }
+
+            if let Some(action) = self.graph.node_menu(ctx) {
+                match action {
+                    NodeAction::Inspect(node_id) => {
+                        self.graph.inspect(node_id);
+                        self.show_inspector = true;
+                    }
+                    NodeAction::Rename { node, alias } => self.graph.rename_node(node, alias),
+                    NodeAction::RenamePorts { node, aliases } => {
+                        self.graph.rename_ports(node, aliases)
+                    }
+                    NodeAction::Collapse { nodes, collapsed } => {
+                        self.graph.set_collapsed(&nodes, collapsed)
+                    }
+                    NodeAction::Hide(nodes) => self.graph.hide_nodes(&nodes),
+                    NodeAction::Copy(text) => ctx.output().copied_text = text,
+                    NodeAction::SetDefault { kind, pw_name } => {
                         self.pipewire_sender
-                            .send(UiMessage::AddLink { from_port, to_port })
+                            .send(UiMessage::SetDefaultNode { kind, pw_name })
                             .expect("Failed to send ui message");
                     }
-                    graph::LinkUpdate::Removed(link_id) => {
+                    NodeAction::MoveTo { node_id, target } => {
                         self.pipewire_sender
-                            .send(UiMessage::RemoveLink(link_id))
+                            .send(UiMessage::SetStreamTarget { node_id, target })
                             .expect("Failed to send ui message");
                     }
+                    NodeAction::Disconnect { nodes, which } => {
+                        let changes = self
+                            .graph
+                            .links_of(&nodes, which)
+                            .into_iter()
+                            .filter_map(|link_id| self.graph.link_ref_of(link_id))
+                            .map(Change::RemoveLink)
+                            .collect();
+                        self.perform(changes);
+                    }
+                    NodeAction::Connect { from, to } => {
+                        let options = self.graph.link_options;
+                        let changes = self
+                            .graph
+                            .channel_pairs(from, to)
+                            .into_iter()
+                            .filter_map(|(from_port, to_port)| {
+                                self.graph.link_ref(from_port, to_port, options)
+                            })
+                            .map(Change::AddLink)
+                            .collect();
+                        self.perform(changes);
+                    }
                 }
             }
 
@@ -342,13 +1165,38 @@ impl eframe::App for GraphUI {
             if self.show_controls {
                 self.controls_window(ctx, ui);
             }
+            if self.show_inspector {
+                self.inspector_window(ctx, ui);
+            }
+            if self.show_devices {
+                self.devices_window(ctx, ui);
+            }
+            if self.show_clients {
+                self.clients_window(ctx, ui);
+            }
+            if self.show_profiler {
+                self.profiler_window(ctx, ui);
+            }
+            if self.show_clock_settings {
+                self.clock_settings_window(ctx, ui);
+            }
+            if self.show_metadata {
+                self.metadata_window(ctx, ui);
+            }
+            if self.show_virtual_device {
+                self.virtual_device_window(ctx, ui);
+            }
 
             egui::TopBottomPanel::bottom("control_hints").show_inside(ui, |ui| {
                 ui.horizontal(|ui| {
                     ui.label("[MMB] Move canvas");
                     ui.label("[LMB] Move node");
                     ui.label("[LMB] Connect port");
+                    ui.label("[CTRL]+[LMB] Toggle selection");
                     ui.label("[ALT]+[LMB] Disconnect port");
+                    ui.label("[RMB] Node/link menu");
+                    ui.label("[DEL] Remove selected links");
+                    ui.label("[CTRL]+[Z] Undo");
                 })
             });
         });
@@ -373,6 +1221,16 @@ fn create_app(
     //Load theme config
     if let Some(storage) = cc.storage {
         graph.theme = eframe::get_value(storage, "theme").unwrap_or_default();
+        graph.graph.port_filter = eframe::get_value(storage, "port_filter").unwrap_or_default();
+        graph.graph.show_client_groups =
+            eframe::get_value(storage, "client_groups").unwrap_or_default();
+        graph.graph.show_driver_groups =
+            eframe::get_value(storage, "driver_groups").unwrap_or_default();
+        graph.graph.link_options = eframe::get_value(storage, "link_options").unwrap_or_default();
+        graph.graph.snap_to_grid = eframe::get_value(storage, "snap_to_grid").unwrap_or_default();
+        graph.graph.aliases = eframe::get_value(storage, "aliases").unwrap_or_default();
+        graph.graph.allow_invalid_links =
+            eframe::get_value(storage, "invalid_links").unwrap_or_default();
     }
 
     Box::new(graph)
@@ -2,9 +2,15 @@ use std::collections::HashMap;
 
 use egui_nodes::{NodeConstructor, PinArgs};
 
-use crate::pipewire_impl::MediaType;
+use crate::pipewire_impl::{DefaultKind, FormatUpdate, MediaType, PortType, Scheduling};
 
-use super::{port::Port, Id, Theme};
+use super::{
+    alias::Aliases,
+    format::Formats,
+    graph::Defaults,
+    port::{Port, PortFilter},
+    Id, Theme,
+};
 
 #[derive(Debug)]
 pub struct Node {
@@ -12,6 +18,11 @@ pub struct Node {
     name: String,
     pw_nodes: Vec<PwNode>,
     pub(super) position: Option<egui::Pos2>,
+    /// Name shown instead of the real one, set by the user
+    pub(super) alias: Option<String>,
+    /// Only the title of collapsed nodes is drawn
+    pub(super) collapsed: bool,
+    pub(super) hidden: bool,
 }
 
 impl Node {
@@ -21,27 +32,84 @@ impl Node {
             name,
             pw_nodes: Vec::new(),
             position: None,
+            alias: None,
+            collapsed: false,
+            hidden: false,
         }
     }
     pub fn name(&self) -> &str {
         &self.name
     }
+    /// Id of the pipewire node owning the port
+    pub fn pw_node_of(&self, port_id: u32) -> Option<u32> {
+        self.pw_nodes
+            .iter()
+            .find(|pw_node| pw_node.ports.contains_key(&port_id))
+            .map(|pw_node| pw_node.id)
+    }
+    /// node.name of the pipewire node owning the port, along with the name of the port
+    pub fn port_key(&self, port_id: u32) -> Option<(&str, &str)> {
+        self.pw_nodes.iter().find_map(|pw_node| {
+            let port = pw_node.ports.get(&port_id)?;
+            Some((pw_node.pw_name.as_str(), port.name()))
+        })
+    }
+    /// The alias of the node if it has one, its name otherwise
+    pub fn display_name(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.name)
+    }
+    pub fn alias(&self) -> Option<&str> {
+        self.alias.as_deref()
+    }
+    pub fn is_collapsed(&self) -> bool {
+        self.collapsed
+    }
+    /// node.name of every pipewire node making up this node
+    pub fn pw_names(&self) -> Vec<&str> {
+        self.pw_nodes
+            .iter()
+            .map(|pw_node| pw_node.pw_name.as_str())
+            .collect()
+    }
+    pub fn pw_ids(&self) -> Vec<u32> {
+        self.pw_nodes.iter().map(|pw_node| pw_node.id).collect()
+    }
+    /// Picks up the aliases of the node and its ports
+    ///
+    /// The node takes the alias of the first of its pipewire nodes which has one
+    pub(super) fn apply_aliases(&mut self, aliases: &Aliases) {
+        self.alias = self
+            .pw_nodes
+            .iter()
+            .find_map(|pw_node| aliases.node(&pw_node.pw_name))
+            .map(str::to_string);
+
+        for pw_node in self.pw_nodes.iter_mut() {
+            for port in pw_node.ports.values_mut() {
+                port.alias = aliases
+                    .port(&pw_node.pw_name, &port.name)
+                    .map(str::to_string);
+            }
+        }
+    }
+    /// Ports which can be linked, in natural order
+    pub fn ports(&self) -> Vec<&Port> {
+        let mut ports = self
+            .pw_nodes
+            .iter()
+            .flat_map(|pw_node| pw_node.ports.values())
+            .filter(|port| port.port_type() != PortType::Unknown)
+            .collect::<Vec<_>>();
+
+        ports.sort_by(|a, b| natord::compare(a.name(), b.name()));
+        ports
+    }
     pub fn id(&self) -> Id {
         self.id
     }
 
-    pub(super) fn add_pw_node(
-        &mut self,
-        id: u32,
-        description: Option<String>,
-        media_type: Option<MediaType>,
-    ) {
-        self.pw_nodes.push(PwNode {
-            id,
-            description,
-            media_type,
-            ports: HashMap::new(),
-        });
+    pub(super) fn add_pw_node(&mut self, pw_node: PwNode) {
+        self.pw_nodes.push(pw_node);
     }
     //TODO: Use pooling
     pub(super) fn remove_pw_node(&mut self, id: u32) -> bool {
@@ -72,32 +140,127 @@ impl Node {
             log::error!("Pipewire node with id: {} was never added", node_id);
         }
     }
+    pub fn get_port(&self, port_id: u32) -> Option<&Port> {
+        self.pw_nodes
+            .iter()
+            .find_map(|pw_node| pw_node.ports.get(&port_id))
+    }
+    pub fn update_format(&mut self, node_id: u32, update: FormatUpdate) {
+        if let Some(pw_node) = self.get_pw_node(node_id) {
+            pw_node.formats.update(update);
+        } else {
+            log::warn!("Pipewire node with id: {} was never added", node_id);
+        }
+    }
+    pub fn update_port_format(&mut self, node_id: u32, port_id: u32, update: FormatUpdate) {
+        match self
+            .get_pw_node(node_id)
+            .and_then(|pw_node| pw_node.ports.get_mut(&port_id))
+        {
+            Some(port) => port.formats.update(update),
+            None => log::warn!("Port with id: {} was never added", port_id),
+        }
+    }
+    pub fn update_scheduling(&mut self, node_id: u32, scheduling: Scheduling) {
+        if let Some(pw_node) = self.get_pw_node(node_id) {
+            pw_node.scheduling = Some(scheduling);
+        } else {
+            log::warn!("Pipewire node with id: {} was never added", node_id);
+        }
+    }
+    /// Ids of the drivers the pipewire nodes of this node follow
+    pub fn driver_ids(&self) -> impl Iterator<Item = u32> + '_ {
+        self.pw_nodes.iter().filter_map(|pw_node| {
+            pw_node
+                .scheduling
+                .as_ref()
+                .and_then(|scheduling| scheduling.driver_id)
+        })
+    }
+    pub fn follows_driver(&self, driver_id: u32) -> bool {
+        self.driver_ids().any(|id| id == driver_id)
+    }
+    /// Negotiated formats of the node, falls back to the formats of its ports if the node itself doesn't report one
+    fn format_summary(&self) -> Option<String> {
+        let mut formats = Vec::new();
+
+        for pw_node in self.pw_nodes.iter() {
+            let node_formats = match &pw_node.formats.negotiated {
+                Some(format) => vec![format],
+                None => pw_node
+                    .ports
+                    .values()
+                    .filter_map(|port| port.formats.negotiated.as_ref())
+                    .collect(),
+            };
+
+            for format in node_formats {
+                if !formats.contains(&format) {
+                    formats.push(format);
+                }
+            }
+        }
+
+        if formats.is_empty() {
+            None
+        } else {
+            Some(
+                formats
+                    .iter()
+                    .map(|format| format.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            )
+        }
+    }
     fn draw_ports<'graph, 'node>(
         ui_node: &'graph mut NodeConstructor<'node>,
         node: &'node PwNode,
         theme: &'node Theme,
+        port_filter: &PortFilter,
         debug: bool,
     ) {
-        let mut ports = node.ports.values().collect::<Vec<_>>();
+        let mut ports = node
+            .ports
+            .values()
+            .filter(|port| port_filter.is_visible(port))
+            .collect::<Vec<_>>();
 
         //Sorts ports based on natural ordering
         ports.sort_by(|a, b| natord::compare(a.name(), b.name()));
 
+        // Ports without a direction can't be linked, they are listed in a separate section instead
+        let (unknown_ports, ports): (Vec<_>, Vec<_>) = ports
+            .into_iter()
+            .partition(|port| matches!(port.port_type(), PortType::Unknown));
+
         for (ix, port) in ports.iter().enumerate() {
-            let (background, hovered) = match &node.media_type {
-                Some(MediaType::Audio) => (theme.audio_port, theme.audio_port_hovered),
-                Some(MediaType::Video) => (theme.video_port, theme.video_port_hovered),
-                Some(MediaType::Midi) => (theme.midi_port, theme.midi_port_hovered),
-                None => (egui::Color32::GRAY, egui::Color32::LIGHT_GRAY),
+            // Ports which don't report a media type inherit it from their node
+            let (background, hovered) = theme.port_colors(port.media_type().or(node.media_type));
+
+            let mut label = match port.channel() {
+                Some(channel) if port.alias.is_none() && !port.name().ends_with(channel) => {
+                    format!("{} ({})", port.name(), channel)
+                }
+                _ => port.display_name().to_string(),
             };
+            if !port.icon().is_empty() {
+                label = format!("{} {}", port.icon(), label);
+            }
             let port_name = {
                 if debug {
-                    format!("{} [{}]", port.name(), port.id())
+                    format!("{} [{}]", label, port.id())
                 } else {
-                    format!("{} ", port.name())
+                    format!("{} ", label)
                 }
             };
 
+            // Aliased ports show their real name on hover
+            let port_tooltip = match port.alias {
+                Some(_) => format!("{}\n{}", port.name(), port.describe()),
+                None => port.describe(),
+            };
+
             let first = debug && ix == 0;
 
             let node_desc_str = if let Some(desc) = &node.description {
@@ -109,67 +272,282 @@ impl Node {
             let node_desc = format!("{} [{}]", node_desc_str, node.id);
 
             match port.port_type() {
-                crate::pipewire_impl::PortType::Input => {
+                PortType::Input => {
                     if first {
                         ui_node.with_input_attribute(
                             port.id() as usize,
                             PinArgs {
+                                shape: port.pin_shape(),
                                 background: Some(background),
                                 hovered: Some(hovered),
                                 ..Default::default()
                             },
                             move |ui| {
                                 ui.colored_label(egui::Color32::WHITE, node_desc);
-                                ui.label(port_name)
+                                ui.label(port_name).on_hover_text(port_tooltip)
                             },
                         );
                     } else {
                         ui_node.with_input_attribute(
                             port.id() as usize,
                             PinArgs {
+                                shape: port.pin_shape(),
                                 background: Some(background),
                                 hovered: Some(hovered),
                                 ..Default::default()
                             },
-                            |ui| ui.label(port_name),
+                            |ui| ui.label(port_name).on_hover_text(port_tooltip),
                         );
                     }
                 }
-                crate::pipewire_impl::PortType::Output => {
+                PortType::Output => {
                     if first {
                         ui_node.with_output_attribute(
                             port.id() as usize,
                             PinArgs {
+                                shape: port.pin_shape(),
                                 background: Some(background),
                                 hovered: Some(hovered),
                                 ..Default::default()
                             },
                             move |ui| {
                                 ui.colored_label(egui::Color32::WHITE, node_desc);
-                                ui.label(port_name)
+                                ui.label(port_name).on_hover_text(port_tooltip)
                             },
                         );
                     } else {
                         ui_node.with_output_attribute(
                             port.id() as usize,
                             PinArgs {
+                                shape: port.pin_shape(),
                                 background: Some(background),
                                 hovered: Some(hovered),
                                 ..Default::default()
                             },
-                            |ui| ui.label(port_name),
+                            |ui| ui.label(port_name).on_hover_text(port_tooltip),
                         );
                     }
                 }
-                crate::pipewire_impl::PortType::Unknown => {}
+                PortType::Unknown => {}
+            }
+        }
+
+        if !unknown_ports.is_empty() {
+            let unknown_ports = unknown_ports
+                .iter()
+                .map(|port| {
+                    let name = if debug {
+                        format!("{} [{}]", port.display_name(), port.id())
+                    } else {
+                        port.display_name().to_string()
+                    };
+                    (name, port.describe())
+                })
+                .collect::<Vec<_>>();
+
+            // Pipewire ids are unique across object types, so the node id doesn't collide with any port id
+            ui_node.with_static_attribute(node.id as usize, move |ui| {
+                ui.vertical(|ui| {
+                    ui.colored_label(theme.warning, "⚠ Unknown direction");
+                    for (name, tooltip) in unknown_ports {
+                        ui.label(name).on_hover_text(tooltip);
+                    }
+                })
+                .response
+            });
+        }
+    }
+
+    /// Kinds of defaults this node is currently set as
+    fn default_kinds(&self, defaults: &Defaults) -> Vec<DefaultKind> {
+        [DefaultKind::Sink, DefaultKind::Source]
+            .into_iter()
+            .filter(|&kind| {
+                defaults.get(kind).map_or(false, |default| {
+                    self.pw_nodes
+                        .iter()
+                        .any(|pw_node| pw_node.pw_name == default)
+                })
+            })
+            .collect()
+    }
+    /// Kinds of defaults this node can be set as, along with the node.name to use
+    pub fn default_candidates(&self) -> Vec<(DefaultKind, &str)> {
+        let mut candidates = Vec::new();
+
+        for pw_node in self.pw_nodes.iter() {
+            if let Some(media_class) = &pw_node.media_class {
+                for kind in [DefaultKind::Sink, DefaultKind::Source] {
+                    if kind.accepts(media_class) {
+                        candidates.push((kind, pw_node.pw_name.as_str()));
+                    }
+                }
             }
         }
+
+        candidates
+    }
+    /// Pipewire ids of the streams in this node, along with the kind of node they can be moved to
+    pub fn streams(&self) -> Vec<(u32, DefaultKind)> {
+        self.pw_nodes
+            .iter()
+            .filter_map(|pw_node| {
+                let kind = DefaultKind::for_stream(pw_node.media_class.as_ref()?)?;
+
+                Some((pw_node.id, kind))
+            })
+            .collect()
+    }
+    /// Whether any of the pipewire nodes of this node belong to the device
+    pub fn belongs_to_device(&self, device_id: u32) -> bool {
+        self.pw_nodes
+            .iter()
+            .any(|pw_node| pw_node.device_id == Some(device_id))
+    }
+    /// Whether any of the pipewire nodes of this node were created by the client
+    pub fn belongs_to_client(&self, client_id: u32) -> bool {
+        self.pw_nodes
+            .iter()
+            .any(|pw_node| pw_node.client_id == Some(client_id))
+    }
+    /// Finds a port by the node.name of its pipewire node and its own name
+    pub fn find_port_by_name(
+        &self,
+        pw_name: &str,
+        name: &str,
+        port_type: PortType,
+    ) -> Option<&Port> {
+        self.pw_nodes
+            .iter()
+            .filter(|pw_node| pw_node.pw_name == pw_name)
+            .flat_map(|pw_node| pw_node.ports.values())
+            .find(|port| port.name() == name && port.port_type() == port_type)
+    }
+    /// Id of the pipewire node with the given node.name
+    pub fn find_pw_node(&self, pw_name: &str) -> Option<u32> {
+        self.pw_nodes
+            .iter()
+            .find(|pw_node| pw_node.pw_name == pw_name)
+            .map(|pw_node| pw_node.id)
+    }
+    /// Audio ports of the node going in one direction, in natural order
+    ///
+    /// Monitor ports are only used by nodes without any other output ports
+    pub fn audio_ports(&self, port_type: PortType) -> Vec<&Port> {
+        let ports = self
+            .pw_nodes
+            .iter()
+            .flat_map(|pw_node| pw_node.ports.values())
+            .filter(|port| {
+                port.port_type() == port_type && port.media_type() == Some(MediaType::Audio)
+            });
+
+        let (monitors, mut ports): (Vec<_>, Vec<_>) = ports.partition(|port| port.flags().monitor);
+        if ports.is_empty() {
+            ports = monitors;
+        }
+
+        ports.sort_by(|a, b| natord::compare(a.name(), b.name()));
+        ports
+    }
+    pub fn has_unknown_ports(&self) -> bool {
+        self.pw_nodes.iter().any(|pw_node| {
+            pw_node
+                .ports
+                .values()
+                .any(|port| matches!(port.port_type(), PortType::Unknown))
+        })
+    }
+    /// Shows detailed information about the node in the inspector
+    pub fn inspect(&self, ui: &mut egui::Ui, theme: &Theme) {
+        ui.heading(self.display_name()).on_hover_text(self.name());
+
+        for pw_node in self.pw_nodes.iter() {
+            let title = format!(
+                "{} [{}]",
+                pw_node.description.as_deref().unwrap_or(self.name()),
+                pw_node.id
+            );
+
+            egui::CollapsingHeader::new(title)
+                .id_source(pw_node.id)
+                .default_open(true)
+                .show(ui, |ui| {
+                    if let Some(device_id) = pw_node.device_id {
+                        ui.label(format!("Device: {}", device_id));
+                    }
+                    if let Some(client_id) = pw_node.client_id {
+                        ui.label(format!("Client: {}", client_id));
+                    }
+                    if let Some(scheduling) = &pw_node.scheduling {
+                        ui.label(format!("State: {:?}", scheduling.state));
+
+                        match scheduling.driver_id {
+                            Some(driver_id) if driver_id == pw_node.id => {
+                                ui.label("Driver");
+                            }
+                            Some(driver_id) => {
+                                ui.label(format!("Follows driver: {}", driver_id));
+                            }
+                            None => {}
+                        }
+                        if let Some((quantum, rate)) = scheduling.latency {
+                            ui.label(format!("Requested latency: {}/{}", quantum, rate));
+                        }
+                    }
+                    ui.label(pw_node.formats.describe());
+
+                    let mut ports = pw_node.ports.values().collect::<Vec<_>>();
+                    ports.sort_by(|a, b| natord::compare(a.name(), b.name()));
+
+                    let sections = [
+                        ("Inputs", PortType::Input),
+                        ("Outputs", PortType::Output),
+                        ("⚠ Unknown direction", PortType::Unknown),
+                    ];
+
+                    for (section, port_type) in sections {
+                        let section_ports = ports
+                            .iter()
+                            .filter(|port| port.port_type() == port_type)
+                            .collect::<Vec<_>>();
+
+                        if section_ports.is_empty() {
+                            continue;
+                        }
+
+                        if port_type == PortType::Unknown {
+                            ui.colored_label(theme.warning, section);
+                        } else {
+                            ui.strong(section);
+                        }
+
+                        egui::Grid::new(("inspector_ports", pw_node.id, section))
+                            .num_columns(2)
+                            .show(ui, |ui| {
+                                for port in section_ports {
+                                    ui.label(format!(
+                                        "{} {} [{}]",
+                                        port.icon(),
+                                        port.display_name(),
+                                        port.id()
+                                    ))
+                                    .on_hover_text(port.name());
+                                    ui.label(port.describe());
+                                    ui.end_row();
+                                }
+                            });
+                    }
+                });
+        }
     }
 
     pub fn draw<'graph, 'node>(
         &'node self,
         ui_node: &'graph mut NodeConstructor<'node>,
         theme: &'node Theme,
+        port_filter: &PortFilter,
+        defaults: &Defaults,
         debug_view: bool,
     ) {
         // let media_type = node.media_type;
@@ -181,32 +559,104 @@ impl Node {
         // };
         let mut media_type = String::new();
         for node in self.pw_nodes.iter() {
-            let media_emoji = match node.media_type {
-                Some(MediaType::Audio) => "🔉",
-                Some(MediaType::Video) => "💻",
-                Some(MediaType::Midi) => "🎹",
-                None => "",
-            };
+            // A node can carry ports of several media types, e.g. audio and midi
+            let media_types = std::iter::once(node.media_type)
+                .chain(node.ports.values().map(|port| port.media_type()));
 
-            if !media_type.contains(media_emoji) {
-                media_type.push_str(&format!(" {}", media_emoji));
+            for port_media_type in media_types {
+                let media_emoji = match port_media_type {
+                    Some(MediaType::Audio) => "🔉",
+                    Some(MediaType::Video) => "💻",
+                    Some(MediaType::Midi) => "🎹",
+                    None => "",
+                };
+
+                if !media_type.contains(media_emoji) {
+                    media_type.push_str(&format!(" {}", media_emoji));
+                }
             }
         }
 
+        let format_summary = self.format_summary();
+
+        let default_marker = if self.default_kinds(defaults).is_empty() {
+            ""
+        } else {
+            "★ "
+        };
+        let collapsed_marker = if self.collapsed { "▸ " } else { "" };
+
         ui_node.with_title(move |ui| {
-            ui.colored_label(theme.text_color, &format!("{} {}", self.name(), media_type))
+            ui.vertical(|ui| {
+                let title = ui.colored_label(
+                    theme.text_color,
+                    &format!(
+                        "{}{}{} {}",
+                        collapsed_marker,
+                        default_marker,
+                        self.display_name(),
+                        media_type
+                    ),
+                );
+                // Aliased nodes show their real name on hover
+                if self.alias.is_some() {
+                    title.on_hover_text(self.name());
+                }
+                if let Some(format_summary) = format_summary {
+                    ui.colored_label(
+                        theme.text_color,
+                        egui::RichText::new(format_summary).small(),
+                    );
+                }
+            })
+            .response
         });
 
+        if self.collapsed {
+            return;
+        }
+
         for node in self.pw_nodes.iter() {
-            Self::draw_ports(ui_node, node, theme, debug_view);
+            Self::draw_ports(ui_node, node, theme, port_filter, debug_view);
         }
     }
 }
 
 #[derive(Debug)]
-struct PwNode {
-    id: u32, //Pipewire id of the node
+pub struct PwNode {
+    id: u32,         //Pipewire id of the node
+    pw_name: String, //node.name of the node
     description: Option<String>,
+    media_class: Option<String>,
     media_type: Option<MediaType>,
+    device_id: Option<u32>, //Pipewire id of the device this node belongs to
+    client_id: Option<u32>, //Pipewire id of the client which created this node
+    scheduling: Option<Scheduling>,
+    formats: Formats,
     ports: HashMap<u32, Port>,
 }
+
+impl PwNode {
+    pub fn new(
+        id: u32,
+        pw_name: String,
+        description: Option<String>,
+        media_class: Option<String>,
+        media_type: Option<MediaType>,
+        device_id: Option<u32>,
+        client_id: Option<u32>,
+    ) -> Self {
+        Self {
+            id,
+            pw_name,
+            description,
+            media_class,
+            media_type,
+            device_id,
+            client_id,
+            scheduling: None,
+            formats: Formats::default(),
+            ports: HashMap::new(),
+        }
+    }
+}
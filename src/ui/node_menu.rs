@@ -0,0 +1,453 @@
+use std::collections::HashMap;
+
+use crate::pipewire_impl::{DefaultKind, PortType};
+
+use super::{node::Node, Id};
+
+/// Actions that can be performed on a node through its context menu
+pub enum NodeAction {
+    /// Show the node in the inspector
+    Inspect(Id),
+    /// Display the node under another name, or its own one if `alias` is `None`
+    Rename {
+        node: Id,
+        alias: Option<String>,
+    },
+    /// Port ids along with their new alias
+    RenamePorts {
+        node: Id,
+        aliases: Vec<(u32, Option<String>)>,
+    },
+    Collapse {
+        nodes: Vec<Id>,
+        collapsed: bool,
+    },
+    Hide(Vec<Id>),
+    /// Put the text in the clipboard
+    Copy(String),
+    SetDefault {
+        kind: DefaultKind,
+        pw_name: String,
+    },
+    /// Link a stream to another node, or back to the default one if `target` is `None`
+    MoveTo {
+        node_id: u32,
+        target: Option<String>,
+    },
+    /// Link every audio channel of a node to another one
+    Connect {
+        from: Id,
+        to: Id,
+    },
+    /// Remove the links of the node, or of every selected node
+    Disconnect {
+        nodes: Vec<Id>,
+        which: Disconnect,
+    },
+}
+
+/// Links removed by [`NodeAction::Disconnect`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Disconnect {
+    Inputs,
+    Outputs,
+    All,
+}
+
+/// What a section of the menu gets to work with
+struct MenuTarget<'a> {
+    node: &'a Node,
+    /// Nodes affected by bulk actions
+    selection: &'a [Id],
+    nodes: &'a HashMap<Id, Node>,
+    stream_targets: &'a HashMap<u32, String>,
+    /// Text being typed in the rename field
+    alias: &'a mut String,
+    /// Port ids and names, along with the text of their rename field
+    port_aliases: &'a mut Vec<(u32, String, String)>,
+}
+
+/// Draws a group of entries, returns the action picked by the user
+type MenuSection = fn(&mut egui::Ui, &mut MenuTarget) -> Option<NodeAction>;
+
+/// Sections of the menu from top to bottom, new actions go in one of them or in a new one
+const SECTIONS: &[MenuSection] = &[view_section, links_section, routing_section, copy_section];
+
+/// Context menu shown when right clicking on a node
+#[derive(Default)]
+pub struct NodeMenu {
+    target: Option<(Id, egui::Pos2)>,
+    /// Nodes affected by bulk actions, the selection if it contains the target
+    nodes: Vec<Id>,
+    alias: String,
+    port_aliases: Vec<(u32, String, String)>,
+    // The click which opened the menu shouldn't close it again
+    just_opened: bool,
+}
+
+impl NodeMenu {
+    pub fn open(&mut self, node: Id, position: egui::Pos2, selection: Vec<Id>) {
+        self.target = Some((node, position));
+        self.nodes = if selection.contains(&node) {
+            selection
+        } else {
+            vec![node]
+        };
+        self.alias.clear();
+        self.just_opened = true;
+    }
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        nodes: &HashMap<Id, Node>,
+        stream_targets: &HashMap<u32, String>,
+    ) -> Option<NodeAction> {
+        let (node_id, position) = self.target?;
+
+        let node = match nodes.get(&node_id) {
+            Some(node) => node,
+            None => {
+                // The node was removed while the menu was open
+                self.target = None;
+                return None;
+            }
+        };
+
+        if self.just_opened {
+            self.alias = node.display_name().to_string();
+            self.port_aliases = node
+                .ports()
+                .into_iter()
+                .map(|port| {
+                    (
+                        port.id(),
+                        port.name().to_string(),
+                        port.display_name().to_string(),
+                    )
+                })
+                .collect();
+        }
+
+        let mut target = MenuTarget {
+            node,
+            selection: &self.nodes,
+            nodes,
+            stream_targets,
+            alias: &mut self.alias,
+            port_aliases: &mut self.port_aliases,
+        };
+        let mut action = None;
+
+        let response = egui::Area::new("node_context_menu")
+            .order(egui::Order::Foreground)
+            .fixed_pos(position)
+            .show(ctx, |ui| {
+                egui::Frame::menu(ui.style()).show(ui, |ui| {
+                    ui.strong(node.display_name());
+                    if target.selection.len() > 1 {
+                        ui.label(format!("{} selected nodes", target.selection.len()));
+                    }
+
+                    for section in SECTIONS {
+                        ui.separator();
+
+                        if let Some(picked) = section(ui, &mut target) {
+                            action = Some(picked);
+                        }
+                    }
+                });
+            })
+            .response;
+
+        if action.is_some() || (response.clicked_elsewhere() && !self.just_opened) {
+            self.target = None;
+        }
+        self.just_opened = false;
+
+        action
+    }
+}
+
+fn view_section(ui: &mut egui::Ui, target: &mut MenuTarget) -> Option<NodeAction> {
+    let mut action = None;
+    let node_id = target.node.id();
+
+    if ui.button("Inspect properties").clicked() {
+        action = Some(NodeAction::Inspect(node_id));
+    }
+
+    ui.menu_button("Rename…", |ui| {
+        let response = ui.text_edit_singleline(&mut *target.alias);
+        let submitted = response.lost_focus() && ui.input().key_pressed(egui::Key::Enter);
+
+        ui.horizontal(|ui| {
+            if ui.button("Rename").clicked() || submitted {
+                action = Some(NodeAction::Rename {
+                    node: node_id,
+                    alias: alias_of(target.node.name(), target.alias),
+                });
+                ui.close_menu();
+            }
+            if target.node.alias().is_some() && ui.button("Reset").clicked() {
+                action = Some(NodeAction::Rename {
+                    node: node_id,
+                    alias: None,
+                });
+                ui.close_menu();
+            }
+        });
+    });
+
+    if !target.port_aliases.is_empty() {
+        ui.menu_button("Rename ports…", |ui| {
+            egui::ScrollArea::vertical()
+                .max_height(300.0)
+                .show(ui, |ui| {
+                    egui::Grid::new("rename_ports_grid")
+                        .num_columns(2)
+                        .show(ui, |ui| {
+                            for (_, name, alias) in target.port_aliases.iter_mut() {
+                                ui.label(name.as_str());
+                                ui.text_edit_singleline(alias);
+                                ui.end_row();
+                            }
+                        });
+                });
+
+            if ui
+                .button("Rename")
+                .on_hover_text("Empty fields give the ports their own name back")
+                .clicked()
+            {
+                let aliases = target
+                    .port_aliases
+                    .iter()
+                    .map(|(port_id, name, alias)| (*port_id, alias_of(name, alias)))
+                    .collect();
+
+                action = Some(NodeAction::RenamePorts {
+                    node: node_id,
+                    aliases,
+                });
+                ui.close_menu();
+            }
+        });
+    }
+
+    let collapsed = target.node.is_collapsed();
+    let label = if collapsed { "Expand" } else { "Collapse" };
+    if ui
+        .button(label)
+        .on_hover_text("Collapsed nodes only show their title, their links are hidden")
+        .clicked()
+    {
+        action = Some(NodeAction::Collapse {
+            nodes: target.selection.to_vec(),
+            collapsed: !collapsed,
+        });
+    }
+
+    if ui
+        .button("Hide")
+        .on_hover_text("Hidden nodes can be shown again from the View menu")
+        .clicked()
+    {
+        action = Some(NodeAction::Hide(target.selection.to_vec()));
+    }
+
+    action
+}
+
+/// Renaming something to its own name, or to nothing, removes its alias
+fn alias_of(name: &str, alias: &str) -> Option<String> {
+    let alias = alias.trim();
+
+    if alias.is_empty() || alias == name {
+        None
+    } else {
+        Some(alias.to_string())
+    }
+}
+
+fn links_section(ui: &mut egui::Ui, target: &mut MenuTarget) -> Option<NodeAction> {
+    let mut action = None;
+    let node_id = target.node.id();
+
+    ui.menu_button("Disconnect", |ui| {
+        for (which, label) in [
+            (Disconnect::Inputs, "All inputs"),
+            (Disconnect::Outputs, "All outputs"),
+            (Disconnect::All, "Everything"),
+        ] {
+            if ui.button(label).clicked() {
+                action = Some(NodeAction::Disconnect {
+                    nodes: target.selection.to_vec(),
+                    which,
+                });
+                ui.close_menu();
+            }
+        }
+    });
+
+    if !target.node.audio_ports(PortType::Output).is_empty() {
+        ui.menu_button("Connect to…", |ui| {
+            if let Some(to) = connect_to_menu(ui, node_id, target.nodes) {
+                action = Some(NodeAction::Connect { from: node_id, to });
+                ui.close_menu();
+            }
+        });
+    }
+
+    action
+}
+
+fn routing_section(ui: &mut egui::Ui, target: &mut MenuTarget) -> Option<NodeAction> {
+    let mut action = None;
+
+    let candidates = target.node.default_candidates();
+    let streams = target.node.streams();
+
+    for &(stream_id, kind) in streams.iter() {
+        let label = if streams.len() > 1 {
+            format!("Move {} to…", stream_id)
+        } else {
+            "Move to…".to_string()
+        };
+
+        ui.menu_button(label, |ui| {
+            if let Some(stream_target) = move_to_menu(
+                ui,
+                kind,
+                target.stream_targets.get(&stream_id),
+                target.nodes,
+            ) {
+                action = Some(NodeAction::MoveTo {
+                    node_id: stream_id,
+                    target: stream_target,
+                });
+                ui.close_menu();
+            }
+        });
+    }
+
+    for (kind, pw_name) in candidates {
+        let label = match kind {
+            DefaultKind::Sink => "Set as default sink",
+            DefaultKind::Source => "Set as default source",
+        };
+
+        if ui.button(label).clicked() {
+            action = Some(NodeAction::SetDefault {
+                kind,
+                pw_name: pw_name.to_string(),
+            });
+        }
+    }
+
+    if streams.is_empty() && candidates.is_empty() {
+        ui.add_enabled(false, egui::Button::new("Set as default"))
+            .on_disabled_hover_text("Only sinks and sources can be set as default");
+    }
+
+    action
+}
+
+fn copy_section(ui: &mut egui::Ui, target: &mut MenuTarget) -> Option<NodeAction> {
+    let mut action = None;
+    let pw_names = target.node.pw_names();
+    let pw_ids = target
+        .node
+        .pw_ids()
+        .iter()
+        .map(|id| id.to_string())
+        .collect::<Vec<_>>();
+
+    ui.menu_button("Copy", |ui| {
+        for (label, text) in [
+            ("Name", target.node.display_name().to_string()),
+            ("node.name", pw_names.join("\n")),
+            ("Id", pw_ids.join(" ")),
+        ] {
+            if ui.button(label).on_hover_text(&text).clicked() {
+                action = Some(NodeAction::Copy(text));
+                ui.close_menu();
+            }
+        }
+    });
+
+    action
+}
+
+/// Lists the nodes with audio inputs, returns the picked one
+fn connect_to_menu(ui: &mut egui::Ui, from: Id, nodes: &HashMap<Id, Node>) -> Option<Id> {
+    let mut picked = None;
+
+    let mut targets = nodes
+        .values()
+        .filter(|node| node.id() != from && !node.audio_ports(PortType::Input).is_empty())
+        .map(|node| (node.display_name(), node.id()))
+        .collect::<Vec<_>>();
+    targets.sort_by(|a, b| natord::compare(a.0, b.0));
+
+    if targets.is_empty() {
+        ui.label("No nodes with audio inputs");
+    }
+
+    for (name, id) in targets {
+        let channels = nodes[&id].audio_ports(PortType::Input).len();
+
+        if ui
+            .button(name)
+            .on_hover_text(format!("{} channels", channels))
+            .clicked()
+        {
+            picked = Some(id);
+        }
+    }
+
+    picked
+}
+
+/// Lists the nodes a stream can be moved to, returns the picked target
+fn move_to_menu(
+    ui: &mut egui::Ui,
+    kind: DefaultKind,
+    current: Option<&String>,
+    nodes: &HashMap<Id, Node>,
+) -> Option<Option<String>> {
+    let mut picked = None;
+
+    if ui.selectable_label(current.is_none(), "Default").clicked() {
+        picked = Some(None);
+    }
+    ui.separator();
+
+    let mut targets = nodes
+        .values()
+        .flat_map(|node| {
+            node.default_candidates()
+                .into_iter()
+                .filter(|&(candidate_kind, _)| candidate_kind == kind)
+                .map(move |(_, pw_name)| (node.display_name(), pw_name))
+        })
+        .collect::<Vec<_>>();
+    targets.sort();
+
+    if targets.is_empty() {
+        ui.label("No compatible nodes");
+    }
+
+    for (name, pw_name) in targets {
+        let selected = current.map_or(false, |current| current == pw_name);
+
+        if ui
+            .selectable_label(selected, name)
+            .on_hover_text(pw_name)
+            .clicked()
+        {
+            picked = Some(Some(pw_name.to_string()));
+        }
+    }
+
+    picked
+}
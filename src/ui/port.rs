@@ -1,17 +1,40 @@
-use crate::pipewire_impl::PortType;
+use egui_nodes::PinShape;
+use serde::{Deserialize, Serialize};
+
+use crate::pipewire_impl::{MediaType, PortFlags, PortType};
+
+use super::format::Formats;
 
 #[derive(Debug)]
 pub struct Port {
     pub id: u32,
     pub name: String,
     pub port_type: PortType,
+    pub media_type: Option<MediaType>,
+    pub channel: Option<String>,
+    pub flags: PortFlags,
+    pub formats: Formats,
+    /// Name shown instead of the real one, set by the user
+    pub alias: Option<String>,
 }
 impl Port {
-    pub fn new(id: u32, name: String, port_type: PortType) -> Self {
+    pub fn new(
+        id: u32,
+        name: String,
+        port_type: PortType,
+        media_type: Option<MediaType>,
+        channel: Option<String>,
+        flags: PortFlags,
+    ) -> Self {
         Self {
             id,
             name,
             port_type,
+            media_type,
+            channel,
+            flags,
+            formats: Formats::default(),
+            alias: None,
         }
     }
     pub fn id(&self) -> u32 {
@@ -20,7 +43,93 @@ impl Port {
     pub fn name(&self) -> &str {
         &self.name
     }
+    /// The alias of the port if it has one, its name otherwise
+    pub fn display_name(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.name)
+    }
     pub fn port_type(&self) -> PortType {
         self.port_type
     }
+    pub fn media_type(&self) -> Option<MediaType> {
+        self.media_type
+    }
+    /// Channel position of the port, like FL or FR
+    pub fn channel(&self) -> Option<&str> {
+        self.channel.as_deref()
+    }
+    pub fn flags(&self) -> PortFlags {
+        self.flags
+    }
+    pub fn pin_shape(&self) -> PinShape {
+        if self.flags.monitor {
+            PinShape::Triangle
+        } else if self.flags.physical {
+            PinShape::QuadFilled
+        } else if self.flags.terminal {
+            PinShape::Quad
+        } else {
+            PinShape::CircleFilled
+        }
+    }
+    /// Icon shown next to the port name, empty for regular ports
+    pub fn icon(&self) -> &'static str {
+        if self.flags.monitor {
+            "👁"
+        } else if self.flags.physical {
+            "🔌"
+        } else if self.flags.terminal {
+            "⏹"
+        } else {
+            ""
+        }
+    }
+    /// Text shown when hovering over the port
+    pub fn describe(&self) -> String {
+        let mut kinds = Vec::new();
+        if self.flags.monitor {
+            kinds.push("Monitor port");
+        }
+        if self.flags.physical {
+            kinds.push("Physical port");
+        }
+        if self.flags.terminal {
+            kinds.push("Terminal port");
+        }
+
+        if kinds.is_empty() {
+            self.formats.describe()
+        } else {
+            format!("{}\n{}", kinds.join(", "), self.formats.describe())
+        }
+    }
+}
+
+/// Decides which kinds of ports are drawn on the graph
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PortFilter {
+    pub show_monitor: bool,
+    pub show_physical: bool,
+    pub show_terminal: bool,
+}
+
+impl Default for PortFilter {
+    fn default() -> Self {
+        Self {
+            show_monitor: true,
+            show_physical: true,
+            show_terminal: true,
+        }
+    }
+}
+
+impl PortFilter {
+    pub fn is_visible(&self, port: &Port) -> bool {
+        let flags = port.flags();
+
+        !(flags.monitor && !self.show_monitor
+            || flags.physical && !self.show_physical
+            // Physical ports are terminal too, they are only hidden by their own filter
+            || flags.terminal && !flags.physical && !self.show_terminal)
+    }
 }
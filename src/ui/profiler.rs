@@ -0,0 +1,198 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use egui::plot::{Line, Plot, PlotPoints};
+
+use crate::pipewire_impl::{DriverProfile, ProfilerInfo, ProfilerSample};
+
+use super::Theme;
+
+/// Number of cycles kept for the load graphs
+const HISTORY_LENGTH: usize = 256;
+/// DSP load above which a driver is highlighted
+const HIGH_LOAD: f32 = 0.8;
+/// Readings older than this are stale, the profiler sends samples several times a second
+const SAMPLE_TIMEOUT: Duration = Duration::from_secs(2);
+
+struct DriverStats {
+    last: DriverProfile,
+    history: VecDeque<f32>,
+}
+
+/// Timing information gathered from the profiler
+#[derive(Default)]
+pub struct ProfilerView {
+    info: Option<ProfilerInfo>,
+    drivers: BTreeMap<u32, DriverStats>, //Driver id to its stats
+    last_sample: Option<Instant>,
+}
+
+impl ProfilerView {
+    pub fn update(&mut self, sample: ProfilerSample) {
+        self.last_sample = Some(Instant::now());
+
+        if sample.info.is_some() {
+            self.info = sample.info;
+        }
+
+        for profile in sample.drivers {
+            let stats = self
+                .drivers
+                .entry(profile.driver.id)
+                .or_insert_with(|| DriverStats {
+                    last: profile.clone(),
+                    history: VecDeque::with_capacity(HISTORY_LENGTH),
+                });
+
+            if stats.history.len() == HISTORY_LENGTH {
+                stats.history.pop_front();
+            }
+            stats.history.push_back(profile.dsp_load);
+            stats.last = profile;
+        }
+    }
+    /// Drivers are removed along with their nodes
+    pub fn remove_driver(&mut self, id: u32) {
+        self.drivers.remove(&id);
+    }
+    pub fn clear(&mut self) {
+        self.info = None;
+        self.drivers.clear();
+        self.last_sample = None;
+    }
+    /// Whether samples are coming in from the profiler
+    pub fn is_receiving(&self) -> bool {
+        self.last_sample
+            .map_or(false, |time| time.elapsed() < SAMPLE_TIMEOUT)
+    }
+    /// Summary of the load of the graph, shown in the status bar
+    pub fn status(&self, ui: &mut egui::Ui, theme: &Theme) {
+        let info = match self.info {
+            Some(info) => info,
+            None => {
+                ui.label("Profiler unavailable");
+                return;
+            }
+        };
+
+        let load = format!("CPU {:.1}%", info.cpu_load[0] * 100.0);
+        if !self.is_receiving() {
+            ui.weak(format!("{} (stale)", load))
+                .on_hover_text("No samples received from the profiler lately");
+        } else if info.cpu_load[0] > HIGH_LOAD {
+            ui.colored_label(theme.warning, load);
+        } else {
+            ui.label(load);
+        }
+
+        let xruns = format!("{} xruns", info.xruns);
+        if info.xruns > 0 {
+            ui.colored_label(theme.warning, xruns);
+        } else {
+            ui.label(xruns);
+        }
+
+        for stats in self.drivers.values() {
+            if stats.last.dsp_load > HIGH_LOAD && self.is_receiving() {
+                ui.colored_label(
+                    theme.warning,
+                    format!(
+                        "⚠ {} {:.0}%",
+                        stats.last.driver.name,
+                        stats.last.dsp_load * 100.0
+                    ),
+                );
+            }
+        }
+    }
+    /// Per driver timings with the history of their load
+    pub fn draw(&self, ui: &mut egui::Ui, theme: &Theme) {
+        if let Some(info) = self.info {
+            ui.label(format!(
+                "CPU load {:.1}% / {:.1}% / {:.1}%",
+                info.cpu_load[0] * 100.0,
+                info.cpu_load[1] * 100.0,
+                info.cpu_load[2] * 100.0
+            ));
+            ui.label(format!("Xruns: {}", info.xruns));
+            ui.separator();
+        }
+
+        if self.drivers.is_empty() {
+            ui.label("No driver has completed a cycle yet");
+        }
+
+        for (id, stats) in self.drivers.iter() {
+            let profile = &stats.last;
+
+            egui::CollapsingHeader::new(format!("{} [{}]", profile.driver.name, id))
+                .id_source(("profiler_driver", id))
+                .default_open(true)
+                .show(ui, |ui| {
+                    let load = format!(
+                        "Quantum {}/{} · DSP {:.1}%",
+                        profile.quantum,
+                        profile.rate,
+                        profile.dsp_load * 100.0
+                    );
+                    if profile.dsp_load > HIGH_LOAD {
+                        ui.colored_label(theme.warning, load);
+                    } else {
+                        ui.label(load);
+                    }
+
+                    let points = stats
+                        .history
+                        .iter()
+                        .enumerate()
+                        .map(|(cycle, load)| [cycle as f64, *load as f64 * 100.0])
+                        .collect::<PlotPoints>();
+
+                    Plot::new(("profiler_history", id))
+                        .height(80.0)
+                        .include_x(0.0)
+                        .include_x(HISTORY_LENGTH as f64)
+                        .include_y(0.0)
+                        .include_y(100.0)
+                        .allow_drag(false)
+                        .allow_zoom(false)
+                        .allow_scroll(false)
+                        .show_x(false)
+                        .show(ui, |plot_ui| plot_ui.line(Line::new(points).name("DSP %")));
+
+                    egui::Grid::new(("profiler_nodes", id))
+                        .num_columns(4)
+                        .striped(true)
+                        .show(ui, |ui| {
+                            ui.strong("Node");
+                            ui.strong("Wait");
+                            ui.strong("Busy");
+                            ui.strong("Xruns");
+                            ui.end_row();
+
+                            for timing in std::iter::once(&profile.driver).chain(&profile.followers)
+                            {
+                                ui.label(&timing.name);
+                                ui.label(format!("{:.1} µs", timing.wait as f64 / 1000.0));
+                                ui.label(format!("{:.1} µs", timing.busy as f64 / 1000.0));
+
+                                match timing.xruns {
+                                    Some(xruns) if xruns > 0 => {
+                                        ui.colored_label(theme.warning, xruns.to_string());
+                                    }
+                                    Some(xruns) => {
+                                        ui.label(xruns.to_string());
+                                    }
+                                    None => {
+                                        ui.label("-");
+                                    }
+                                }
+                                ui.end_row();
+                            }
+                        });
+                });
+        }
+    }
+}
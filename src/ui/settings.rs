@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+
+use super::Theme;
+
+/// Clock properties of the settings metadata: key, label and a short explanation
+const CLOCK_SETTINGS: [(&str, &str, &str); 7] = [
+    ("clock.rate", "Rate", "Default sample rate of the graph"),
+    (
+        "clock.allowed-rates",
+        "Allowed rates",
+        "Rates the graph may switch to, like [ 44100 48000 ]",
+    ),
+    (
+        "clock.quantum",
+        "Quantum",
+        "Default number of samples per cycle",
+    ),
+    (
+        "clock.min-quantum",
+        "Min quantum",
+        "Smallest quantum nodes can request",
+    ),
+    (
+        "clock.max-quantum",
+        "Max quantum",
+        "Largest quantum nodes can request",
+    ),
+    (
+        "clock.force-quantum",
+        "Force quantum",
+        "Overrides the quantum of every driver, 0 disables it",
+    ),
+    (
+        "clock.force-rate",
+        "Force rate",
+        "Overrides the rate of every driver, 0 disables it",
+    ),
+];
+
+/// Contents of the settings metadata, along with the values being edited
+#[derive(Default)]
+pub struct ClockSettings {
+    values: HashMap<String, String>,
+    edits: HashMap<&'static str, String>,
+}
+
+impl ClockSettings {
+    /// A `None` key clears all the settings
+    pub fn update(&mut self, key: Option<String>, value: Option<String>) {
+        // Edits of changed settings would be stale
+        match (key, value) {
+            (Some(key), Some(value)) => {
+                self.edits.remove(key.as_str());
+                self.values.insert(key, value);
+            }
+            (Some(key), None) => {
+                self.edits.remove(key.as_str());
+                self.values.remove(&key);
+            }
+            (None, _) => self.clear(),
+        }
+    }
+    pub fn clear(&mut self) {
+        self.values.clear();
+        self.edits.clear();
+    }
+    /// Shows an editor for every clock setting, returns the settings changed by the user
+    pub fn draw(&mut self, ui: &mut egui::Ui, theme: &Theme) -> Vec<(String, String)> {
+        let mut changes = Vec::new();
+
+        if self.values.is_empty() {
+            ui.label("No settings metadata, is pipewire running?");
+            return changes;
+        }
+
+        egui::Grid::new("clock_settings_grid")
+            .num_columns(3)
+            .show(ui, |ui| {
+                for (key, label, hint) in CLOCK_SETTINGS {
+                    let current = self.values.get(key).map(|value| value.as_str());
+
+                    ui.label(label).on_hover_text(format!("{}\n{}", key, hint));
+
+                    let edit = self
+                        .edits
+                        .entry(key)
+                        .or_insert_with(|| current.unwrap_or_default().to_string());
+
+                    let valid = is_valid(key, edit);
+                    let text_color = if valid { None } else { Some(theme.warning) };
+
+                    ui.add(
+                        egui::TextEdit::singleline(edit)
+                            .desired_width(140.0)
+                            .text_color_opt(text_color),
+                    );
+
+                    ui.horizontal(|ui| {
+                        let changed = current != Some(edit.trim());
+
+                        if ui
+                            .add_enabled(valid && changed, egui::Button::new("Apply"))
+                            .clicked()
+                        {
+                            changes.push((key.to_string(), edit.trim().to_string()));
+                        }
+                        if ui
+                            .add_enabled(changed, egui::Button::new("Revert"))
+                            .clicked()
+                        {
+                            *edit = current.unwrap_or_default().to_string();
+                        }
+                    });
+                    ui.end_row();
+                }
+            });
+
+        changes
+    }
+}
+
+fn is_valid(key: &str, value: &str) -> bool {
+    let value = value.trim();
+
+    if key == "clock.allowed-rates" {
+        match value
+            .strip_prefix('[')
+            .and_then(|value| value.strip_suffix(']'))
+        {
+            Some(rates) => rates
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|rate| !rate.is_empty())
+                .all(|rate| rate.parse::<u32>().is_ok()),
+            None => false,
+        }
+    } else {
+        value.parse::<u32>().is_ok()
+    }
+}
@@ -0,0 +1,99 @@
+use crate::pipewire_impl::{VirtualDevice, VirtualDeviceKind};
+
+/// Usual channel layouts, by channel count
+fn default_positions(channels: usize) -> &'static str {
+    match channels {
+        1 => "MONO",
+        2 => "FL,FR",
+        3 => "FL,FR,LFE",
+        4 => "FL,FR,RL,RR",
+        5 => "FL,FR,FC,RL,RR",
+        6 => "FL,FR,FC,LFE,RL,RR",
+        7 => "FL,FR,FC,LFE,RL,RR,RC",
+        _ => "FL,FR,FC,LFE,RL,RR,SL,SR",
+    }
+}
+
+/// Form used to create null sinks and sources
+pub struct VirtualDeviceDialog {
+    name: String,
+    kind: VirtualDeviceKind,
+    channels: usize,
+    positions: String,
+    linger: bool,
+}
+
+impl Default for VirtualDeviceDialog {
+    fn default() -> Self {
+        Self {
+            name: String::from("Virtual Sink"),
+            kind: VirtualDeviceKind::Sink,
+            channels: 2,
+            positions: default_positions(2).to_string(),
+            linger: false,
+        }
+    }
+}
+
+impl VirtualDeviceDialog {
+    fn positions(&self) -> Vec<String> {
+        self.positions
+            .split(',')
+            .map(|position| position.trim().to_uppercase())
+            .filter(|position| !position.is_empty())
+            .collect()
+    }
+    /// Returns the device to create once the user confirms
+    pub fn draw(&mut self, ui: &mut egui::Ui) -> Option<VirtualDevice> {
+        let mut device = None;
+
+        egui::Grid::new("virtual_device_grid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Name");
+                ui.text_edit_singleline(&mut self.name);
+                ui.end_row();
+
+                ui.label("Type");
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut self.kind, VirtualDeviceKind::Sink, "Sink");
+                    ui.radio_value(&mut self.kind, VirtualDeviceKind::Source, "Source");
+                });
+                ui.end_row();
+
+                ui.label("Channels");
+                if ui
+                    .add(egui::DragValue::new(&mut self.channels).clamp_range(1..=8))
+                    .changed()
+                {
+                    self.positions = default_positions(self.channels).to_string();
+                }
+                ui.end_row();
+
+                ui.label("Positions");
+                ui.text_edit_singleline(&mut self.positions)
+                    .on_hover_text("Comma separated channel positions, like FL,FR");
+                ui.end_row();
+            });
+
+        ui.checkbox(&mut self.linger, "Keep after pw-viz exits");
+
+        let positions = self.positions();
+        let valid = !self.name.trim().is_empty() && positions.len() == self.channels;
+
+        if !valid {
+            ui.label("The name can't be empty and there must be a position for every channel");
+        }
+
+        if ui.add_enabled(valid, egui::Button::new("Create")).clicked() {
+            device = Some(VirtualDevice {
+                name: self.name.trim().to_string(),
+                kind: self.kind,
+                positions,
+                linger: self.linger,
+            });
+        }
+
+        device
+    }
+}